@@ -1,10 +1,19 @@
-use std::{default::Default, fs, path, path::PathBuf, sync::Arc};
+use std::{
+    cmp::Ordering,
+    default::Default,
+    fs, path,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use crate::{
     kits,
     kits::Shared,
     player,
-    player::{CommandGo, CommandUi, Player, kits::FfmpegKit},
+    player::{
+        AlphaBackground, ColorAdjust, CommandGo, CommandUi, DisplayMode, ExportPreset, ExportTask, FrameSampling, FramesOutput, Player, Playlist,
+        PlaylistEntry, RepeatMode, ScreenshotFormat, Settings, ToneMap, VideoFilter, VideoFilters, WatchHistory, WatchRecord, kits::FfmpegKit,
+    },
 };
 
 pub struct AppUi {
@@ -13,13 +22,49 @@ pub struct AppUi {
     media_path: String,
     no_scale: bool,
     auto_play_next: bool,
+    /// go to the sibling dirs at the end of the dir, when play next file
+    recurse_dirs: bool,
     command_ui: Shared<CommandUi>,
     /// ui界面使用
     pub command_go_ui: Shared<CommandGo>,
+    watch_history: WatchHistory,
+    /// the position to resume the current file, show the resume window if it is some
+    resume_ms: Option<i64>,
+    playlist: Playlist,
+    show_playlist: bool,
+    /// the next file and its player, it is opened before the end of current file, for gapless playback
+    next_player: Option<(String, Player)>,
+    next_player_opened: bool,
+    /// the video filters of the user, they are applied to every player
+    video_filters: VideoFilters,
+    display_mode: DisplayMode,
+    settings: Settings,
+    /// the operator of hdr to sdr
+    tone_map: ToneMap,
+    alpha_background: AlphaBackground,
+    screenshot_format: ScreenshotFormat,
+    /// include the burned subtitles in the screenshot
+    screenshot_subtitles: bool,
+    /// the export of the clip, it is kept after it is finished to show the result
+    export_task: Option<ExportTask>,
+    export_preset: ExportPreset,
+    /// sample the key frames, or else every n seconds
+    frames_key_frames: bool,
+    frames_interval_s: i64,
+    frames_output: FramesOutput,
+    /// the id of the last go command, a spinner is shown until it is completed
+    go_id: u64,
+    /// the picture adjustments while a slider is dragged, they are applied at most every [AppUi::COLOR_APPLY_MS]
+    color_dragging: Option<ColorAdjust>,
+    color_applied_ms: i64,
 }
 
 impl AppUi {
     pub(crate) fn handle_key_player(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        // the keys are typed into a text field, eg: the filter spec
+        if ctx.wants_keyboard_input() {
+            return;
+        }
         for e in &ui.input(|k| k.events.clone()) {
             if let Some(player) = &mut self.player {
                 if let egui::Event::Key {
@@ -31,10 +76,18 @@ impl AppUi {
                             self.command_ui.set(CommandUi::Close);
                         }
                         egui::Key::ArrowLeft => {
-                            player.go_back_ui(&self.command_go_ui);
+                            if modifiers.ctrl {
+                                player.pre_chapter();
+                            } else {
+                                self.go_id = player.go_back_ui(&self.command_go_ui);
+                            }
                         }
                         egui::Key::ArrowRight => {
-                            player.go_ahead_ui(&self.command_go_ui);
+                            if modifiers.ctrl {
+                                player.next_chapter();
+                            } else {
+                                self.go_id = player.go_ahead_ui(&self.command_go_ui);
+                            }
                         }
                         egui::Key::Tab => {
                             if modifiers.ctrl {
@@ -55,16 +108,77 @@ impl AppUi {
                             player.clicked_player();
                         }
                         egui::Key::PageDown => {
-                            let file = AppUi::next_file(&self.media_path);
-                            self.open_file(ctx, file.into());
+                            self.open_next_media(ctx, true, false);
                         }
                         egui::Key::PageUp => {
-                            let file = AppUi::pre_file(&self.media_path);
-                            self.open_file(ctx, file.into());
+                            self.open_next_media(ctx, false, false);
                         }
                         egui::Key::F1 => {
                             self.command_ui.set(CommandUi::FullscreenToggle);
                         }
+                        egui::Key::F2 => {
+                            self.show_playlist = !self.show_playlist;
+                        }
+                        egui::Key::R => {
+                            player.rotate(!modifiers.shift);
+                        }
+                        egui::Key::H => {
+                            player.flip(true);
+                        }
+                        egui::Key::V => {
+                            player.flip(false);
+                        }
+                        egui::Key::A => {
+                            self.set_display_mode(self.display_mode.next());
+                        }
+                        egui::Key::Num0 => {
+                            player.reset_zoom();
+                        }
+                        egui::Key::B | egui::Key::C | egui::Key::S | egui::Key::G | egui::Key::U => {
+                            let sign = if modifiers.shift { -1. } else { 1. };
+                            let mut adjust = player.color_adjust();
+                            match key {
+                                egui::Key::B => adjust.brightness += 0.02 * sign,
+                                egui::Key::C => adjust.contrast += 0.05 * sign,
+                                egui::Key::S => adjust.saturation += 0.05 * sign,
+                                egui::Key::G => adjust.gamma += 0.05 * sign,
+                                _ => adjust.hue += 5. * sign,
+                            }
+                            self.set_color_adjust(adjust);
+                        }
+                        egui::Key::P => {
+                            if modifiers.shift {
+                                player.screenshot(ctx, None, true, self.screenshot_subtitles);
+                            } else {
+                                player.screenshot(ctx, Some(self.screenshot_format), false, self.screenshot_subtitles);
+                            }
+                        }
+                        egui::Key::Backspace => {
+                            self.set_color_adjust(ColorAdjust::default());
+                        }
+                        egui::Key::I => {
+                            player.mark_in();
+                        }
+                        egui::Key::O => {
+                            player.mark_out();
+                        }
+                        egui::Key::E if !(modifiers.ctrl || modifiers.alt || modifiers.command || modifiers.mac_cmd) => {
+                            // one export at a time
+                            if self.export_task.as_ref().is_none_or(|t| t.is_finished()) {
+                                let task = if modifiers.shift {
+                                    player.export_encoded(self.export_preset)
+                                } else {
+                                    player.export_clip()
+                                };
+                                if task.is_some() {
+                                    self.export_task = task;
+                                }
+                            }
+                        }
+                        egui::Key::Num1 | egui::Key::Num2 | egui::Key::Num3 | egui::Key::Num4 | egui::Key::Num5 | egui::Key::Num6 | egui::Key::Num7 => {
+                            let i = key.name().parse::<usize>().unwrap_or(1) - 1;
+                            self.set_display_mode(DisplayMode::ALL[i.min(DisplayMode::ALL.len() - 1)]);
+                        }
                         _ => {}
                     }
                 }
@@ -82,6 +196,9 @@ impl AppUi {
                         egui::Key::F1 => {
                             self.command_ui.set(CommandUi::FullscreenToggle);
                         }
+                        egui::Key::F2 => {
+                            self.show_playlist = !self.show_playlist;
+                        }
                         _ => {}
                     },
                     egui::Event::PointerButton {
@@ -102,71 +219,138 @@ impl AppUi {
         }
     }
 
-    pub(crate) fn next_file(file: &str) -> String {
+    pub(crate) fn next_file(file: &str, recurse: bool) -> String {
+        Self::sibling_file(file, true, recurse)
+    }
+
+    pub(crate) fn pre_file(file: &str, recurse: bool) -> String {
+        Self::sibling_file(file, false, recurse)
+    }
+
+    /// the next(or previous) media file in the dir of the file, by natural order.
+    /// if `recurse` is true, go to the sibling dirs at the end of the dir
+    fn sibling_file(file: &str, forward: bool, recurse: bool) -> String {
         let path_file = path::PathBuf::from(file);
-        if path_file.file_name().is_none() {
+        let (Some(name), Some(dir)) = (path_file.file_name(), path_file.parent()) else {
             return String::default();
+        };
+        let name = name.to_string_lossy();
+        let files = Self::media_files(dir);
+        let found = if forward {
+            files.iter().find(|f| name_cmp(f, &name) == Ordering::Greater)
+        } else {
+            files.iter().rev().find(|f| name_cmp(f, &name) == Ordering::Less)
+        };
+        if let Some(f) = found {
+            return f.to_string_lossy().to_string();
+        }
+        if recurse && let Some(f) = Self::sibling_dir_file(dir, forward) {
+            return f.to_string_lossy().to_string();
         }
-        let mut files = Vec::new();
-        match fs::read_dir(path_file.parent().unwrap()) {
+        // go back to the first(or last) one
+        let f = if forward { files.first() } else { files.last() };
+        f.map(|f| f.to_string_lossy().to_string()).unwrap_or_default()
+    }
+
+    /// the first(or last) media file in the next(or previous) sibling dirs
+    fn sibling_dir_file(dir: &Path, forward: bool) -> Option<PathBuf> {
+        let name = dir.file_name()?.to_string_lossy().to_string();
+        let mut dirs = Self::dir_entries(dir.parent()?, |p| p.is_dir());
+        if !forward {
+            dirs.reverse();
+        }
+        let target = if forward { Ordering::Greater } else { Ordering::Less };
+        dirs.into_iter().filter(|d| name_cmp(d, &name) == target).find_map(|d| {
+            let files = Self::media_files(&d);
+            if forward { files.into_iter().next() } else { files.into_iter().next_back() }
+        })
+    }
+
+    /// the media files of the dir, sorted by natural order
+    fn media_files(dir: &Path) -> Vec<PathBuf> {
+        Self::dir_entries(dir, |p| p.is_file() && FfmpegKit::is_media_file(p))
+    }
+
+    fn dir_entries(dir: &Path, filter: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+        let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
             Err(e) => {
                 log::error!("{e}");
-                return String::default();
-            }
-            Ok(read_dir) => {
-                for f in read_dir.flatten() {
-                    files.push(f.file_name());
-                }
+                return Vec::new();
             }
+            Ok(read_dir) => read_dir.flatten().map(|f| f.path()).filter(|p| filter(p)).collect(),
+        };
+        entries.sort_by(|a, b| name_cmp(a, &b.file_name().unwrap_or_default().to_string_lossy()));
+        entries
+    }
+
+    /// the next file to play, follow the playlist if the `from` is the current of it, or else the files of the dir.
+    /// if `auto` is true, the current file is finished, and return empty if there is no file to play
+    fn next_media(&mut self, from: &str, auto: bool) -> String {
+        if self.playlist.current_entry().is_some_and(|e| e.path == from) {
+            self.playlist.next(auto).map(|e| e.path.clone()).unwrap_or_default()
+        } else {
+            AppUi::next_file(from, self.recurse_dirs)
         }
+    }
 
-        files.sort();
-        if let Ok(i) = files.binary_search(&path_file.file_name().unwrap().to_os_string()) {
-            let i = { if i == files.len() - 1 { 0 } else { i + 1 } };
-            let f = files.get(i);
-            return path_file.parent().unwrap().join(f.unwrap()).to_string_lossy().to_string();
+    /// the next file like [AppUi::next_media] when the current file is finished, but the playlist is not changed
+    fn peek_next_media(&self, from: &str) -> String {
+        if self.playlist.current_entry().is_some_and(|e| e.path == from) {
+            self.playlist.peek_next(true).map(|e| e.path.clone()).unwrap_or_default()
+        } else {
+            AppUi::next_file(from, self.recurse_dirs)
         }
-        String::default()
     }
 
-    pub(crate) fn pre_file(file: &str) -> String {
-        let path_file = path::PathBuf::from(file);
-        if path_file.file_name().is_none() {
-            return String::default();
+    fn pre_media(&mut self, from: &str) -> String {
+        if self.playlist.current_entry().is_some_and(|e| e.path == from) {
+            self.playlist.pre().map(|e| e.path.clone()).unwrap_or_default()
+        } else {
+            AppUi::pre_file(from, self.recurse_dirs)
         }
-        let mut files = Vec::new();
-        match fs::read_dir(path_file.parent().unwrap()) {
-            Err(e) => {
-                log::error!("{e}");
-                return String::default();
+    }
+
+    /// open the next(or previous) media, skip the files which can not be opened
+    fn open_next_media(&mut self, ctx: &egui::Context, forward: bool, auto: bool) -> bool {
+        const MAX_TRY: usize = 64;
+        let mut from = self.media_path.clone();
+        for _ in 0..MAX_TRY {
+            let file = if forward { self.next_media(&from, auto) } else { self.pre_media(&from) };
+            if file.is_empty() {
+                return false;
             }
-            Ok(read_dir) => {
-                for f in read_dir.flatten() {
-                    files.push(f.file_name());
-                }
+            if self.open_file(ctx, file.clone().into()) {
+                return true;
             }
+            log::info!("skip the file: {file}");
+            if file == from || file == self.media_path {
+                // all files are tried
+                return false;
+            }
+            from = file;
         }
-
-        files.sort();
-        if let Ok(i) = files.binary_search(&path_file.file_name().unwrap().to_os_string()) {
-            let i = { if i == 0 { files.len() - 1 } else { i - 1 } };
-            let f = files.get(i);
-            return path_file.parent().unwrap().join(f.unwrap()).to_string_lossy().to_string();
-        }
-        String::default()
+        false
     }
 
     fn handle_command_ui(&mut self, ctx: &egui::Context) {
         let cmd = self.command_ui.get();
         self.command_ui.set(CommandUi::None);
         if cmd == CommandUi::None {
+            if self.auto_play_next {
+                self.preopen_next(ctx);
+            }
             //check play finish
             if self.auto_play_next
                 && let Some(p) = &self.player
-                && p.play_ctrl.video_finished()
+                && !p.repeat_current
+                && p.play_ctrl.play_finished()
             {
-                let file = AppUi::next_file(&self.media_path);
-                if self.open_file(ctx, file.into())
+                if let Some((file, next_player)) = self.next_player.take() {
+                    self.set_player(file, next_player);
+                    if let Some(p) = &mut self.player {
+                        p.start();
+                    }
+                } else if self.open_next_media(ctx, true, true)
                     && let Some(p) = &mut self.player
                 {
                     p.start();
@@ -201,35 +385,45 @@ impl AppUi {
             }
             CommandUi::MinimizedTrue => ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true)),
             CommandUi::MinimizedFalse => ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false)),
-            CommandUi::Close => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+            CommandUi::Close => {
+                self.save_history();
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close)
+            }
         }
     }
 
     fn main_frame(&mut self, ctx: &egui::Context, frame: egui::Frame) {
         self.right_panel(ctx, frame);
+        self.playlist_panel(ctx, frame);
         egui::CentralPanel::default().frame(frame).show(ctx, |rigth_ui| {
             {
-                let file = rigth_ui.input(|s| match s.raw.dropped_files.first() {
-                    Some(egui::DroppedFile { path: Some(first), .. }) => Some(first.clone()),
-                    _ => None,
-                });
-                if let Some(f) = file {
+                let files: Vec<PathBuf> = rigth_ui.input(|s| s.raw.dropped_files.iter().filter_map(|f| f.path.clone()).collect());
+                if files.len() > 1 {
+                    // add all to the playlist
+                    for f in files.iter().filter(|f| !Playlist::is_playlist_file(f)) {
+                        self.playlist.add(PlaylistEntry::new(&f.to_string_lossy()));
+                    }
+                    self.show_playlist = true;
+                }
+                if let Some(f) = files.into_iter().next() {
                     self.open_file(ctx, f);
                 }
             }
             if let Some(player) = &mut self.player {
-                let p = {
-                    if self.no_scale {
-                        egui::Vec2::new(player.width as f32, player.height as f32)
-                    } else {
-                        AppUi::compute_player_size(
-                            egui::Vec2::new(player.width as f32, player.height as f32),
-                            egui::Vec2::new(rigth_ui.min_rect().width(), rigth_ui.min_rect().height()),
-                        )
-                    }
-                };
-                rigth_ui.centered_and_justified(|ui| player.ui(ui, [p.x, p.y]));
+                if player.audio_only() {
+                    Self::music_ui(rigth_ui, player, &self.media_path);
+                } else {
+                    let (p, uv) = {
+                        if self.no_scale {
+                            (player.display_size(), egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1., 1.)))
+                        } else {
+                            player.layout(self.display_mode, egui::Vec2::new(rigth_ui.min_rect().width(), rigth_ui.min_rect().height()))
+                        }
+                    };
+                    rigth_ui.centered_and_justified(|ui| player.ui(ui, [p.x, p.y], uv));
+                }
             }
+            self.resume_window(ctx);
 
             let rect = {
                 const WIDTH: f32 = 30.0;
@@ -248,29 +442,48 @@ impl AppUi {
         });
     }
 
+    /// the compact layout of the music file, the cover art and the tags under it
+    fn music_ui(ui: &mut egui::Ui, player: &mut Player, media_path: &str) {
+        const COVER_SIZE: f32 = 360.;
+        const TAGS_HEIGHT: f32 = 80.;
+        let available = ui.available_size();
+        let max = egui::Vec2::splat(COVER_SIZE).min(available - egui::vec2(0., TAGS_HEIGHT)).max(egui::Vec2::ZERO);
+        let (p, uv) = player.layout(DisplayMode::Fit, max);
+        ui.vertical_centered(|ui| {
+            ui.add_space(((available.y - p.y - TAGS_HEIGHT) / 2.).max(0.));
+            player.ui(ui, [p.x, p.y], uv);
+            ui.add_space(8.);
+            ui.heading(player.tags.title_or_file(media_path));
+            if !player.tags.artist.is_empty() {
+                ui.label(&player.tags.artist);
+            }
+            let album = player.tags.album_line();
+            if !album.is_empty() {
+                ui.weak(album);
+            }
+        });
+    }
+
     fn select_file() -> Option<PathBuf> {
         let names = FfmpegKit::demuxers();
         // &["mp4", "mkv", "ogg", "webm", "wmv", "mov", "avi", "mp3", "flv"]
-        rfd::FileDialog::new().add_filter("videos", &names).pick_file()
+        rfd::FileDialog::new()
+            .add_filter("videos", &names)
+            .add_filter("playlist", &Playlist::EXTENSIONS)
+            .pick_file()
     }
 
     fn open_file(&mut self, ctx: &egui::Context, buf: PathBuf) -> bool {
-        self.media_path = buf.to_string_lossy().to_string();
-        if !self.media_path.is_empty() {
+        if Playlist::is_playlist_file(&buf) {
+            return self.open_playlist(ctx, &buf);
+        }
+        let media_path = buf.to_string_lossy().to_string();
+        if !media_path.is_empty() {
             //create a new texture, do not use the old one
             let texture_handle = Player::default_texture_handle(ctx);
-            match Player::new(ctx, texture_handle, self.command_ui.clone(), &self.media_path) {
-                Ok(mut new_player) => {
-                    let mut played = false;
-                    if let Some(old_player) = &self.player {
-                        new_player.tab_seek_ms = old_player.tab_seek_ms;
-                        new_player.audio_volume.set(old_player.audio_volume.get());
-                        played = old_player.player_state.get() == player::PlayerState::Playing;
-                    }
-                    self.player = Some(new_player);
-                    if played {
-                        self.player.as_mut().unwrap().start();
-                    }
+            match Player::new(ctx, texture_handle, self.command_ui.clone(), &media_path) {
+                Ok(new_player) => {
+                    self.set_player(media_path, new_player);
                     true
                 }
                 Err(e) => {
@@ -283,6 +496,196 @@ impl AppUi {
         }
     }
 
+    /// replace the current player by the new one, it keeps the settings of the old player
+    fn set_player(&mut self, media_path: String, mut new_player: Player) {
+        self.save_history();
+        self.resume_ms = None;
+        self.drop_next_player();
+        self.media_path = media_path;
+        self.playlist.select_path(&self.media_path);
+        let mut played = false;
+        if let Some(old_player) = &self.player {
+            new_player.tab_seek_ms = old_player.tab_seek_ms;
+            new_player.repeat_current = old_player.repeat_current;
+            new_player.audio_volume.set(old_player.audio_volume.get());
+            played = old_player.player_state.get() == player::PlayerState::Playing;
+        }
+        new_player.set_video_filters(&self.video_filters);
+        new_player.set_crop_detect(self.display_mode == DisplayMode::CropBlackBars);
+        new_player.set_color_adjust(ColorAdjust::load(&self.settings, &self.media_path));
+        new_player.set_tone_map(self.tone_map);
+        new_player.alpha_background = self.alpha_background;
+        if let Some(record) = self.watch_history.get(&self.media_path) {
+            new_player.audio_volume.set(record.volume);
+            self.resume_ms = record.resume_ms();
+        }
+        self.player = Some(new_player);
+        if played {
+            self.player.as_mut().unwrap().start();
+        }
+    }
+
+    /// open the next file before the end of current file, it will be played without gap
+    fn preopen_next(&mut self, ctx: &egui::Context) {
+        let Some(p) = &self.player else {
+            return;
+        };
+        // seeked back, the next player is opened again when it is near the end
+        if self.next_player_opened && !p.packet_finished() && p.duration_ms - p.elapsed_ms() > player::GAPLESS_PREOPEN_MS {
+            self.drop_next_player();
+            return;
+        }
+        if self.next_player_opened || p.repeat_current || p.player_state.get() != player::PlayerState::Playing {
+            return;
+        }
+        if !p.packet_finished() && p.duration_ms - p.elapsed_ms() > player::GAPLESS_PREOPEN_MS {
+            return;
+        }
+        self.next_player_opened = true;
+        // do not change the playlist, until the next player is played
+        let file = self.peek_next_media(&self.media_path);
+        if file.is_empty() {
+            return;
+        }
+        let Some(p) = &self.player else {
+            return;
+        };
+        match Player::new_next(ctx, p, &file) {
+            Err(e) => log::error!("{e}"),
+            Ok(mut next_player) => {
+                log::info!("pre-open the next file: {file}");
+                next_player.set_video_filters(&self.video_filters);
+                next_player.set_crop_detect(self.display_mode == DisplayMode::CropBlackBars);
+                next_player.set_color_adjust(ColorAdjust::load(&self.settings, &file));
+                next_player.set_tone_map(self.tone_map);
+                next_player.alpha_background = self.alpha_background;
+                self.next_player = Some((file, next_player));
+            }
+        }
+    }
+
+    /// load the playlist and play the first entry
+    fn open_playlist(&mut self, ctx: &egui::Context, file: &Path) -> bool {
+        match Playlist::load(file) {
+            Err(e) => {
+                log::error!("{e}");
+                false
+            }
+            Ok(mut playlist) => {
+                playlist.repeat = self.playlist.repeat;
+                playlist.set_shuffle(self.playlist.shuffle());
+                self.playlist = playlist;
+                self.show_playlist = true;
+                match self.playlist.next(false).map(|e| e.path.clone()) {
+                    Some(f) => self.open_file(ctx, f.into()),
+                    None => false,
+                }
+            }
+        }
+    }
+
+    fn playlist_panel(&mut self, ctx: &egui::Context, frame: egui::Frame) {
+        if !self.show_playlist {
+            return;
+        }
+        egui::SidePanel::left("playlist_panel")
+            .frame(frame)
+            .min_width(0.0)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Add").clicked()
+                        && let Some(files) = rfd::FileDialog::new().add_filter("videos", &FfmpegKit::demuxers()).pick_files()
+                    {
+                        for f in files {
+                            self.playlist.add(PlaylistEntry::new(&f.to_string_lossy()));
+                        }
+                    }
+                    if ui.button("Add current").clicked() && !self.media_path.is_empty() {
+                        self.playlist.add(PlaylistEntry::new(&self.media_path));
+                        self.playlist.select_path(&self.media_path);
+                    }
+                    if ui.button("Clear").clicked() {
+                        self.playlist.clear();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Load").clicked()
+                        && let Some(f) = rfd::FileDialog::new().add_filter("playlist", &Playlist::EXTENSIONS).pick_file()
+                    {
+                        self.open_playlist(ctx, &f);
+                    }
+                    if ui.button("Save").clicked()
+                        && let Some(f) = rfd::FileDialog::new()
+                            .add_filter("playlist", &Playlist::EXTENSIONS)
+                            .set_file_name("playlist.m3u8")
+                            .save_file()
+                        && let Err(e) = self.playlist.save(&f)
+                    {
+                        log::error!("{e}");
+                    }
+                });
+                ui.horizontal(|ui| {
+                    let mut shuffle = self.playlist.shuffle();
+                    if ui.checkbox(&mut shuffle, "Shuffle").changed() {
+                        self.playlist.set_shuffle(shuffle);
+                    }
+                    egui::ComboBox::from_id_salt("playlist_repeat")
+                        .selected_text(self.playlist.repeat.name())
+                        .show_ui(ui, |ui| {
+                            for mode in RepeatMode::ALL {
+                                ui.selectable_value(&mut self.playlist.repeat, mode, mode.name());
+                            }
+                        });
+                });
+                ui.separator();
+
+                enum Action {
+                    Play(usize),
+                    Up(usize),
+                    Down(usize),
+                    Remove(usize),
+                }
+                let mut action = None;
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let current = self.playlist.current();
+                    for (i, entry) in self.playlist.entries().iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("▲").clicked() {
+                                action = Some(Action::Up(i));
+                            }
+                            if ui.small_button("▼").clicked() {
+                                action = Some(Action::Down(i));
+                            }
+                            if ui.small_button("✕").clicked() {
+                                action = Some(Action::Remove(i));
+                            }
+                            if ui
+                                .selectable_label(current == Some(i), entry.display_name())
+                                .on_hover_text(&entry.path)
+                                .clicked()
+                            {
+                                action = Some(Action::Play(i));
+                            }
+                        });
+                    }
+                });
+                match action {
+                    None => {}
+                    Some(Action::Play(i)) => {
+                        if let Some(f) = self.playlist.set_current(i).map(|e| e.path.clone()) {
+                            self.open_file(ctx, f.into());
+                        }
+                    }
+                    Some(Action::Up(i)) => self.playlist.move_entry(i, i.saturating_sub(1)),
+                    Some(Action::Down(i)) => self.playlist.move_entry(i, i + 1),
+                    Some(Action::Remove(i)) => {
+                        self.playlist.remove(i);
+                    }
+                }
+            });
+    }
+
     fn right_panel(&mut self, ctx: &egui::Context, frame: egui::Frame) {
         if !self.collapse {
             egui::SidePanel::right("right_panel")
@@ -316,7 +719,9 @@ impl AppUi {
                         });
                         ui.horizontal(|ui| {
                             if ui.button("Stop").clicked() {
+                                self.save_history();
                                 self.player = None;
+                                self.drop_next_player();
                             }
                         });
                         ui.horizontal(|ui| {
@@ -334,6 +739,16 @@ impl AppUi {
                             }
                         });
                         ui.checkbox(&mut self.no_scale, "no scale");
+                        let mut display_mode = self.display_mode;
+                        egui::ComboBox::from_label("Display (A)").selected_text(display_mode.name()).show_ui(ui, |ui| {
+                            for (i, mode) in DisplayMode::ALL.into_iter().enumerate() {
+                                ui.selectable_value(&mut display_mode, mode, format!("{} ({})", mode.name(), i + 1));
+                            }
+                        });
+                        if display_mode != self.display_mode {
+                            self.set_display_mode(display_mode);
+                        }
+                        ui.checkbox(&mut self.show_playlist, "Playlist");
 
                         if !self.media_path.is_empty() {
                             ui.horizontal(|ui| {
@@ -344,8 +759,7 @@ impl AppUi {
                             ui.label(self.media_path.clone());
                             ui.horizontal(|ui| {
                                 if ui.button("Pre file").clicked() {
-                                    let file = AppUi::pre_file(&self.media_path);
-                                    self.open_file(ctx, file.into());
+                                    self.open_next_media(ctx, false, false);
                                 }
                             });
                             ui.horizontal(|ui| {
@@ -355,11 +769,40 @@ impl AppUi {
                             });
                             ui.horizontal(|ui| {
                                 if ui.button("Next file").clicked() {
-                                    let file = AppUi::next_file(&self.media_path);
-                                    self.open_file(ctx, file.into());
+                                    self.open_next_media(ctx, true, false);
                                 }
                             });
                         }
+                        if let Some(hdr) = self.player.as_ref().and_then(|p| p.hdr()) {
+                            let mut tone_map = self.tone_map;
+                            egui::ComboBox::from_label(format!("Tone Map ({})", hdr.name()))
+                                .selected_text(tone_map.name())
+                                .show_ui(ui, |ui| {
+                                    for t in ToneMap::ALL {
+                                        ui.selectable_value(&mut tone_map, t, t.name());
+                                    }
+                                });
+                            if tone_map != self.tone_map {
+                                self.set_tone_map(tone_map);
+                            }
+                        }
+                        if self.player.as_ref().is_some_and(|p| p.video_alpha.get()) {
+                            let mut background = self.alpha_background;
+                            egui::ComboBox::from_label("Background").selected_text(background.name()).show_ui(ui, |ui| {
+                                for b in AlphaBackground::ALL {
+                                    ui.selectable_value(&mut background, b, b.name());
+                                }
+                            });
+                            if background != self.alpha_background {
+                                self.alpha_background = background;
+                                self.settings.set(Self::ALPHA_BACKGROUND_KEY, background.name());
+                                if let Some(p) = &mut self.player {
+                                    p.alpha_background = background;
+                                }
+                            }
+                        }
+                        let mut set_adjust = None;
+                        let mut filters_changed = false;
                         if let Some(player) = &mut self.player {
                             ui.horizontal(|ui| {
                                 let (mut go_amount, mut go_packet) = match self.command_go_ui.get() {
@@ -431,7 +874,10 @@ impl AppUi {
 
                             ui.horizontal(|ui| {
                                 if ui.button("Go").clicked() {
-                                    player.go_ahead_ui(&self.command_go_ui);
+                                    self.go_id = player.go_ahead_ui(&self.command_go_ui);
+                                }
+                                if !player.commands.is_completed(self.go_id) {
+                                    ui.spinner();
                                 }
                             });
 
@@ -466,6 +912,225 @@ impl AppUi {
                             ui.horizontal(|ui| {
                                 ui.checkbox(&mut self.auto_play_next, "Auto Play Next");
                             });
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut player.repeat_current, "Repeat Current");
+                            });
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.recurse_dirs, "Recurse Dirs");
+                            });
+
+                            ui.horizontal(|ui| {
+                                if ui.button("Rotate L").on_hover_text("Rotate Left (Shift+R)").clicked() {
+                                    player.rotate(false);
+                                }
+                                if ui.button("Rotate R").on_hover_text("Rotate Right (R)").clicked() {
+                                    player.rotate(true);
+                                }
+                                if ui.button("Flip H").on_hover_text("Flip Horizontal (H)").clicked() {
+                                    player.flip(true);
+                                }
+                                if ui.button("Flip V").on_hover_text("Flip Vertical (V)").clicked() {
+                                    player.flip(false);
+                                }
+                            });
+
+                            ui.collapsing("Screenshot", |ui| {
+                                ui.horizontal(|ui| {
+                                    if ui.button("Save").on_hover_text("Save Screenshot (P)").clicked() {
+                                        player.screenshot(ctx, Some(self.screenshot_format), false, self.screenshot_subtitles);
+                                    }
+                                    if ui.button("Copy").on_hover_text("Copy to Clipboard (Shift+P)").clicked() {
+                                        player.screenshot(ctx, None, true, self.screenshot_subtitles);
+                                    }
+                                });
+                                let mut format = self.screenshot_format;
+                                egui::ComboBox::from_label("Format").selected_text(format.name()).show_ui(ui, |ui| {
+                                    for f in ScreenshotFormat::ALL {
+                                        ui.selectable_value(&mut format, f, f.name());
+                                    }
+                                });
+                                if format != self.screenshot_format {
+                                    self.screenshot_format = format;
+                                    self.settings.set(Self::SCREENSHOT_FORMAT_KEY, format.name());
+                                }
+                                if ui.checkbox(&mut self.screenshot_subtitles, "Subtitles").changed() {
+                                    self.settings.set(Self::SCREENSHOT_SUBTITLES_KEY, &self.screenshot_subtitles.to_string());
+                                }
+                            });
+
+                            ui.collapsing("Clip", |ui| {
+                                ui.horizontal(|ui| {
+                                    if ui.button("In").on_hover_text("Mark In (I)").clicked() {
+                                        player.mark_in();
+                                    }
+                                    if ui.button("Out").on_hover_text("Mark Out (O)").clicked() {
+                                        player.mark_out();
+                                    }
+                                    if ui.button("Clear").clicked() {
+                                        player.clear_marks();
+                                    }
+                                });
+                                let range = player.clip_range();
+                                if let Some((start, end)) = range {
+                                    ui.label(format!(
+                                        "{} ~ {}",
+                                        Player::format_duration(chrono::Duration::milliseconds(start)),
+                                        Player::format_duration(chrono::Duration::milliseconds(end))
+                                    ));
+                                }
+                                let running = self.export_task.as_ref().is_some_and(|t| !t.is_finished());
+                                if ui
+                                    .add_enabled(!running && range.is_some(), egui::Button::new("Export"))
+                                    .on_hover_text("Export without Encoding (E)")
+                                    .clicked()
+                                {
+                                    self.export_task = player.export_clip();
+                                }
+                                ui.horizontal(|ui| {
+                                    if ui
+                                        .add_enabled(!running && range.is_some(), egui::Button::new("Encode"))
+                                        .on_hover_text("Export with the Filters (Shift+E)")
+                                        .clicked()
+                                    {
+                                        self.export_task = player.export_encoded(self.export_preset);
+                                    }
+                                    let mut preset = self.export_preset;
+                                    egui::ComboBox::from_id_salt("export_preset").selected_text(preset.name()).show_ui(ui, |ui| {
+                                        for p in ExportPreset::ALL {
+                                            ui.selectable_value(&mut preset, p, p.name());
+                                        }
+                                    });
+                                    if preset != self.export_preset {
+                                        self.export_preset = preset;
+                                        self.settings.set(Self::EXPORT_PRESET_KEY, preset.name());
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    if ui
+                                        .add_enabled(!running, egui::Button::new("Frames"))
+                                        .on_hover_text("Export the Frames of the Clip, or the File if it is not Marked")
+                                        .clicked()
+                                    {
+                                        let sampling = if self.frames_key_frames {
+                                            FrameSampling::KeyFrames
+                                        } else {
+                                            FrameSampling::Interval(self.frames_interval_s * 1000)
+                                        };
+                                        self.export_task = Some(player.export_frames(sampling, self.frames_output));
+                                    }
+                                    egui::ComboBox::from_id_salt("frames_output")
+                                        .selected_text(self.frames_output.name())
+                                        .show_ui(ui, |ui| {
+                                            for o in FramesOutput::ALL {
+                                                ui.selectable_value(&mut self.frames_output, o, o.name());
+                                            }
+                                        });
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.checkbox(&mut self.frames_key_frames, "Key Frames");
+                                    if !self.frames_key_frames {
+                                        ui.add(egui::DragValue::new(&mut self.frames_interval_s).range(1..=3600).prefix("every ").suffix(" s"));
+                                    }
+                                });
+                                if let Some(task) = &self.export_task {
+                                    match task.result() {
+                                        None => {
+                                            ui.horizontal(|ui| {
+                                                ui.add(egui::ProgressBar::new(task.progress()).show_percentage());
+                                                if ui.button("Cancel").clicked() {
+                                                    task.cancel();
+                                                }
+                                            });
+                                            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+                                        }
+                                        Some(Ok(())) => {
+                                            ui.label(format!("exported {}", task.file.display()));
+                                        }
+                                        Some(Err(e)) => {
+                                            ui.colored_label(egui::Color32::RED, format!("export failed: {e}"));
+                                        }
+                                    }
+                                }
+                            });
+
+                            ui.collapsing("Video Filters", |ui| {
+                                let mut changed = false;
+                                for filter in VideoFilter::ALL {
+                                    let mut enabled = self.video_filters.is_enabled(filter);
+                                    if ui.checkbox(&mut enabled, filter.label()).changed() {
+                                        self.video_filters.set_enabled(filter, enabled);
+                                        changed = true;
+                                    }
+                                }
+                                ui.label("libavfilter: ");
+                                let re = ui.add(egui::TextEdit::singleline(&mut self.video_filters.raw).hint_text("eq=contrast=1.2,hflip"));
+                                if re.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                                    changed = true;
+                                }
+                                if ui.button("Apply").clicked() {
+                                    changed = true;
+                                }
+                                if let Some(e) = player.filter_error() {
+                                    ui.colored_label(egui::Color32::RED, format!("not applied: {e}"));
+                                }
+                                if changed {
+                                    player.set_video_filters(&self.video_filters);
+                                    player.refresh_frame();
+                                    // the next player has the old filters
+                                    filters_changed = true;
+                                    if let Err(e) = self.video_filters.save(&mut self.settings) {
+                                        log::error!("{e}");
+                                    }
+                                }
+                            });
+
+                            ui.collapsing("Color", |ui| {
+                                let mut adjust = self.color_dragging.unwrap_or_else(|| player.color_adjust());
+                                let responses = [
+                                    ui.add(egui::Slider::new(&mut adjust.brightness, -1.0..=1.0).text("Brightness (B)")),
+                                    ui.add(egui::Slider::new(&mut adjust.contrast, 0.0..=2.0).text("Contrast (C)")),
+                                    ui.add(egui::Slider::new(&mut adjust.saturation, 0.0..=3.0).text("Saturation (S)")),
+                                    ui.add(egui::Slider::new(&mut adjust.gamma, 0.1..=10.0).logarithmic(true).text("Gamma (G)")),
+                                    ui.add(egui::Slider::new(&mut adjust.hue, -180.0..=180.0).suffix("°").text("Hue (U)")),
+                                ];
+                                let mut changed = responses.iter().any(|r| r.changed() || r.drag_stopped());
+                                if ui.button("Reset").on_hover_text("Reset (Backspace)").clicked() {
+                                    adjust = ColorAdjust::default();
+                                    changed = true;
+                                }
+                                // every change rebuilds the filters, and decodes the frame again if it is paused
+                                if responses.iter().any(|r| r.dragged()) {
+                                    self.color_dragging = Some(adjust);
+                                    if changed && chrono::Utc::now().timestamp_millis() - self.color_applied_ms >= Self::COLOR_APPLY_MS {
+                                        set_adjust = Some(adjust);
+                                    }
+                                } else {
+                                    self.color_dragging = None;
+                                    if changed {
+                                        set_adjust = Some(adjust);
+                                    }
+                                }
+                            });
+
+                            if !player.chapters.is_empty() {
+                                ui.collapsing("Chapters", |ui| {
+                                    let mut seek_ms = None;
+                                    for chapter in &player.chapters {
+                                        if ui.button(&chapter.title).clicked() {
+                                            seek_ms = Some(chapter.start_ms);
+                                        }
+                                    }
+                                    if let Some(ms) = seek_ms {
+                                        player.seek_ms(ms);
+                                    }
+                                });
+                            }
+                        }
+                        if let Some(adjust) = set_adjust {
+                            self.set_color_adjust(adjust);
+                        }
+                        if filters_changed {
+                            self.drop_next_player();
                         }
                     });
                     // ui.allocate_rect(ui.available_rect_before_wrap(), egui::Sense::hover());
@@ -474,6 +1139,11 @@ impl AppUi {
     }
 }
 
+/// compare the file name of the path with the name, by natural order
+fn name_cmp(path: &Path, name: &str) -> Ordering {
+    player::kits::natural_cmp(&path.file_name().unwrap_or_default().to_string_lossy(), name)
+}
+
 unsafe impl Send for AppUi {}
 
 unsafe impl Sync for AppUi {}
@@ -481,14 +1151,145 @@ unsafe impl Sync for AppUi {}
 impl AppUi {
     fn new(cc: &eframe::CreationContext<'_>) -> Self {
         Self::set_font(&cc.egui_ctx);
+        let settings = Settings::load_default();
+        let display_mode = settings.get(Self::DISPLAY_MODE_KEY).and_then(DisplayMode::from_name).unwrap_or_default();
+        let tone_map = settings.get(Self::TONE_MAP_KEY).and_then(ToneMap::from_name).unwrap_or_default();
+        let screenshot_format = settings
+            .get(Self::SCREENSHOT_FORMAT_KEY)
+            .and_then(ScreenshotFormat::from_name)
+            .unwrap_or_default();
+        let screenshot_subtitles = settings.get(Self::SCREENSHOT_SUBTITLES_KEY).is_none_or(|v| v == "true");
+        let export_preset = settings.get(Self::EXPORT_PRESET_KEY).and_then(ExportPreset::from_name).unwrap_or_default();
+        let alpha_background = settings
+            .get(Self::ALPHA_BACKGROUND_KEY)
+            .and_then(AlphaBackground::from_name)
+            .unwrap_or_default();
         Self {
             collapse: true,
             player: None,
             media_path: String::default(),
             no_scale: false,
             auto_play_next: false,
+            recurse_dirs: false,
             command_ui: Shared::new(CommandUi::None),
             command_go_ui: Shared::new(CommandGo::GoMs(5000)),
+            watch_history: WatchHistory::load_default(),
+            resume_ms: None,
+            playlist: Playlist::default(),
+            show_playlist: false,
+            next_player: None,
+            next_player_opened: false,
+            video_filters: VideoFilters::load(&settings),
+            display_mode,
+            settings,
+            tone_map,
+            alpha_background,
+            screenshot_format,
+            screenshot_subtitles,
+            export_task: None,
+            export_preset,
+            frames_key_frames: false,
+            frames_interval_s: 10,
+            frames_output: FramesOutput::default(),
+            go_id: 0,
+            color_dragging: None,
+            color_applied_ms: 0,
+        }
+    }
+
+    const DISPLAY_MODE_KEY: &'static str = "display_mode";
+    const TONE_MAP_KEY: &'static str = "tone_map";
+    const ALPHA_BACKGROUND_KEY: &'static str = "alpha_background";
+    const SCREENSHOT_FORMAT_KEY: &'static str = "screenshot_format";
+    const SCREENSHOT_SUBTITLES_KEY: &'static str = "screenshot_subtitles";
+    const EXPORT_PRESET_KEY: &'static str = "export_preset";
+    const COLOR_APPLY_MS: i64 = 200;
+
+    /// change the display mode of the players, and remember it
+    fn set_display_mode(&mut self, mode: DisplayMode) {
+        self.display_mode = mode;
+        self.settings.set(Self::DISPLAY_MODE_KEY, mode.name());
+        if let Some(p) = &mut self.player {
+            p.set_crop_detect(mode == DisplayMode::CropBlackBars);
+        }
+        self.drop_next_player();
+    }
+
+    /// change the tone mapping of the players, and remember it
+    fn set_tone_map(&mut self, tone_map: ToneMap) {
+        self.tone_map = tone_map;
+        self.settings.set(Self::TONE_MAP_KEY, tone_map.name());
+        if let Some(p) = &mut self.player {
+            p.set_tone_map(tone_map);
+            p.refresh_frame();
+        }
+        self.drop_next_player();
+    }
+
+    /// change the picture adjustments of current file, and remember them. they are saved after the slider is released
+    fn set_color_adjust(&mut self, adjust: ColorAdjust) {
+        self.color_applied_ms = chrono::Utc::now().timestamp_millis();
+        if let Some(p) = &mut self.player {
+            p.set_color_adjust(adjust);
+            p.refresh_frame();
+            p.color_adjust().store(&mut self.settings, &self.media_path);
+        }
+        if self.color_dragging.is_none()
+            && let Err(e) = self.settings.save()
+        {
+            log::error!("{e}");
+        }
+        self.drop_next_player();
+    }
+
+    /// drop the pre-opened next player, it is opened again with the new settings, see [AppUi::preopen_next]
+    fn drop_next_player(&mut self) {
+        self.next_player = None;
+        self.next_player_opened = false;
+    }
+
+    /// update the record of current file, and save the watch history
+    fn save_history(&mut self) {
+        if let Some(p) = &self.player
+            && let Some(mut record) = WatchRecord::new(&self.media_path, p.elapsed_ms(), p.duration_ms, p.audio_volume.get())
+        {
+            // finished, or reopened a watched file but not played. the audio only file has no video to finish
+            if p.video_finished()
+                || p.player_state.get() == player::PlayerState::EndOfFile
+                || self.watch_history.get(&self.media_path).is_some_and(|r| r.watched && record.position_ms == 0)
+            {
+                record.watched = true;
+            }
+            self.watch_history.update(record);
+            if let Err(e) = self.watch_history.save() {
+                log::error!("{e}");
+            }
+        }
+    }
+
+    fn resume_window(&mut self, ctx: &egui::Context) {
+        let (Some(ms), Some(player)) = (self.resume_ms, &mut self.player) else {
+            return;
+        };
+        let mut close = false;
+        egui::Window::new("Resume")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!("Resume from {} ?", Player::format_duration(chrono::Duration::milliseconds(ms))));
+                ui.horizontal(|ui| {
+                    if ui.button("Resume").clicked() {
+                        player.seek_ms(ms);
+                        close = true;
+                    }
+                    if ui.button("Start over").clicked() {
+                        close = true;
+                    }
+                });
+            });
+        if close {
+            self.resume_ms = None;
         }
     }
     fn collapse_str(&self) -> &'static str {
@@ -499,7 +1300,7 @@ impl AppUi {
     }
 
     pub fn run_app() {
-        let title = "Door Player";
+        let title = player::kits::APP_ID;
         let ops = eframe::NativeOptions {
             renderer: eframe::Renderer::Wgpu,
             centered: true,
@@ -521,24 +1322,6 @@ impl AppUi {
         }
     }
 
-    fn compute_player_size(video_size: egui::Vec2, ui_size: egui::Vec2) -> egui::Vec2 {
-        let mut re = egui::Vec2::splat(0.0);
-        if ui_size.x > 0.0 && ui_size.y > 0.0 && video_size.x > 0.0 && video_size.y > 0.0 {
-            let x_ = ui_size.x / video_size.x;
-            let y_ = ui_size.y / video_size.y;
-            if x_ > y_ {
-                re.x = video_size.x * y_;
-                re.y = ui_size.y;
-            } else if x_ == y_ {
-                re.x = ui_size.x;
-                re.y = ui_size.y;
-            } else {
-                re.x = ui_size.x;
-                re.y = video_size.y * x_;
-            }
-        }
-        re
-    }
     /// set the font to support chinese
     fn set_font(ctx: &egui::Context) {
         let ctx: egui::Context = ctx.clone();
@@ -588,6 +1371,9 @@ impl AppUi {
 
 impl eframe::App for AppUi {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if ctx.input(|i| i.viewport().close_requested()) {
+            self.save_history();
+        }
         self.handle_command_ui(ctx);
         let frame = egui::Frame::default();
         // self.title_bar(ctx, frame);
@@ -16,6 +16,8 @@ pub struct AudioPlayFrame {
     pub pts: i64,
     pub duration: i64,
     pub timestamp: i64,
+    /// the seek serial when it is decoded
+    pub serial: u64,
 }
 
 // impl AudioPlayFrame {
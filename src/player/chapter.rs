@@ -0,0 +1,38 @@
+use crate::player::kits::timestamp_to_millisecond;
+
+/// chapter of the container(mkv, mp4 ...), the time is millisecond
+#[derive(Default, Clone, Debug)]
+pub struct Chapter {
+    pub id: i64,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub title: String,
+}
+
+impl Chapter {
+    pub fn chapters(input: &ffmpeg::format::context::Input) -> Vec<Chapter> {
+        let mut chapters: Vec<Chapter> = input
+            .chapters()
+            .map(|c| {
+                let time_base = c.time_base();
+                let title = match c.metadata().get("title") {
+                    Some(t) if !t.is_empty() => t.to_string(),
+                    _ => format!("Chapter {}", c.index() + 1),
+                };
+                Chapter {
+                    id: c.id(),
+                    start_ms: timestamp_to_millisecond(c.start(), time_base),
+                    end_ms: timestamp_to_millisecond(c.end(), time_base),
+                    title,
+                }
+            })
+            .collect();
+        chapters.sort_by_key(|c| c.start_ms);
+        chapters
+    }
+
+    /// the index of the chapter which contains the ms
+    pub fn index_at(chapters: &[Chapter], ms: i64) -> Option<usize> {
+        chapters.iter().rposition(|c| c.start_ms <= ms)
+    }
+}
@@ -0,0 +1,92 @@
+use crate::player::Settings;
+
+/// the picture adjustments, they are applied by the filters "eq" and "hue"
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorAdjust {
+    /// -1.0 ~ 1.0, default 0
+    pub brightness: f32,
+    /// 0.0 ~ 2.0, default 1
+    pub contrast: f32,
+    /// 0.0 ~ 3.0, default 1
+    pub saturation: f32,
+    /// 0.1 ~ 10.0, default 1
+    pub gamma: f32,
+    /// degrees, -180 ~ 180, default 0
+    pub hue: f32,
+}
+
+impl Default for ColorAdjust {
+    fn default() -> Self {
+        Self {
+            brightness: 0.,
+            contrast: 1.,
+            saturation: 1.,
+            gamma: 1.,
+            hue: 0.,
+        }
+    }
+}
+
+impl ColorAdjust {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// keep the values in the ranges of the filters
+    pub fn clamp(&mut self) {
+        self.brightness = self.brightness.clamp(-1., 1.);
+        self.contrast = self.contrast.clamp(0., 2.);
+        self.saturation = self.saturation.clamp(0., 3.);
+        self.gamma = self.gamma.clamp(0.1, 10.);
+        self.hue = self.hue.clamp(-180., 180.);
+    }
+
+    /// the filter chain string of libavfilter, empty if there is no adjustment
+    pub fn spec(&self) -> String {
+        let mut specs = Vec::new();
+        if self.brightness != 0. || self.contrast != 1. || self.saturation != 1. || self.gamma != 1. {
+            specs.push(format!(
+                "eq=brightness={:.3}:contrast={:.3}:saturation={:.3}:gamma={:.3}",
+                self.brightness, self.contrast, self.saturation, self.gamma
+            ));
+        }
+        if self.hue != 0. {
+            specs.push(format!("hue=h={:.1}", self.hue));
+        }
+        specs.join(",")
+    }
+
+    /// the adjustment of the file in the [Settings], the default if there is none
+    pub fn load(settings: &Settings, path: &str) -> Self {
+        settings.get(&Self::key(path)).and_then(Self::from_value).unwrap_or_default()
+    }
+
+    /// set the adjustment of the file to the settings without saving, the default adjustment is removed
+    pub fn store(&self, settings: &mut Settings, path: &str) {
+        if self.is_default() {
+            settings.remove(&Self::key(path));
+        } else {
+            settings.insert(&Self::key(path), &self.to_value());
+        }
+    }
+
+    fn key(path: &str) -> String {
+        format!("color_adjust:{path}")
+    }
+
+    /// brightness, contrast, saturation, gamma, hue. split by ","
+    fn to_value(&self) -> String {
+        format!("{},{},{},{},{}", self.brightness, self.contrast, self.saturation, self.gamma, self.hue)
+    }
+
+    fn from_value(value: &str) -> Option<Self> {
+        let mut it = value.split(',');
+        Some(Self {
+            brightness: it.next()?.parse().ok()?,
+            contrast: it.next()?.parse().ok()?,
+            saturation: it.next()?.parse().ok()?,
+            gamma: it.next()?.parse().ok()?,
+            hue: it.next()?.parse().ok()?,
+        })
+    }
+}
@@ -1,5 +1,15 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
 use bytemuck::NoUninit;
 
+use crate::kits::Deque;
+
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub enum CommandUi {
     None,
@@ -29,3 +39,113 @@ pub enum CommandGo {
 }
 
 unsafe impl NoUninit for CommandGo {}
+
+/// the command to the player threads, the id is used to report the completion
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Command {
+    pub id: u64,
+    pub go: CommandGo,
+}
+
+/// the commands from the ui to the player threads, no command is lost.
+/// consecutive seeks are collapsed to the latest one, and consecutive [CommandGo::GoMs] are added up.
+/// the completion is tracked by id, because the commands are done by different threads
+#[derive(Clone, Default)]
+pub struct CommandQueue {
+    queue: Deque<Command>,
+    next_id: Arc<AtomicU64>,
+    /// the ids which are not completed, and the id of the command which completes them
+    pending: Arc<parking_lot::Mutex<BTreeMap<u64, u64>>>,
+}
+
+impl CommandQueue {
+    /// push the command, return the id of it. the command collapsed into a later one is completed with the later one
+    pub fn push(&self, go: CommandGo) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut queue = self.queue.lock();
+        if go == CommandGo::None {
+            return id;
+        }
+        let mut pending = self.pending.lock();
+        pending.insert(id, id);
+        // the collapsed one is completed by the new one
+        let mut collapse = |last_id: u64| pending.values_mut().filter(|by| **by == last_id).for_each(|by| *by = id);
+        match (queue.back_mut(), go) {
+            (Some(last), CommandGo::Seek(_)) if matches!(last.go, CommandGo::Seek(_) | CommandGo::GoMs(_)) => {
+                collapse(last.id);
+                *last = Command { id, go };
+            }
+            (
+                Some(Command {
+                    id: last_id,
+                    go: CommandGo::GoMs(last_ms),
+                }),
+                CommandGo::GoMs(ms),
+            ) => {
+                collapse(*last_id);
+                *last_id = id;
+                *last_ms += ms;
+            }
+            _ => queue.push_back(Command { id, go }),
+        }
+        id
+    }
+
+    /// pop the first command which the f accepts, every thread takes its own commands in order
+    pub fn pop_if(&self, f: impl Fn(&CommandGo) -> bool) -> Option<Command> {
+        let mut queue = self.queue.lock();
+        let index = queue.iter().position(|c| f(&c.go))?;
+        queue.remove(index)
+    }
+
+    /// the command is done, and the commands collapsed into it
+    pub fn complete(&self, id: u64) {
+        self.pending.lock().retain(|_, by| *by != id);
+    }
+
+    pub fn is_completed(&self, id: u64) -> bool {
+        !self.pending.lock().contains_key(&id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::player::{Command, CommandGo, CommandQueue};
+
+    #[test]
+    fn test_coalesce() {
+        let commands = CommandQueue::default();
+        commands.push(CommandGo::Frame(1));
+        commands.push(CommandGo::GoMs(5000));
+        commands.push(CommandGo::GoMs(5000));
+        let id = commands.push(CommandGo::Seek(10));
+        let last = commands.push(CommandGo::Seek(20));
+        assert!(last > id);
+        assert_eq!(
+            commands.pop_if(|go| !matches!(go, CommandGo::Frame(_))),
+            Some(Command {
+                id: last,
+                go: CommandGo::Seek(20)
+            })
+        );
+        assert_eq!(commands.pop_if(|go| !matches!(go, CommandGo::Frame(_))), None);
+        let frame = commands.pop_if(|go| matches!(go, CommandGo::Frame(_))).expect("frame");
+        assert_eq!(frame.go, CommandGo::Frame(1));
+        assert_eq!(commands.pop_if(|_| true), None);
+
+        // the seek is still running on the other thread when the frame is done
+        commands.complete(frame.id);
+        assert!(commands.is_completed(frame.id));
+        assert!(!commands.is_completed(id));
+        commands.complete(last);
+        assert!(commands.is_completed(id));
+        assert!(commands.is_completed(last));
+
+        let first = commands.push(CommandGo::GoMs(5000));
+        let id = commands.push(CommandGo::GoMs(-2000));
+        assert_eq!(commands.pop_if(|_| true), Some(Command { id, go: CommandGo::GoMs(3000) }));
+        assert!(!commands.is_completed(first));
+        commands.complete(id);
+        assert!(commands.is_completed(first));
+    }
+}
@@ -17,3 +17,13 @@ pub const AV_TIME_BASE_RATIONAL: Rational = Rational(1, AV_TIME_BASE);
 pub const MILLISECOND_TIME_BASE: Rational = Rational(1, 1000);
 /// if dont move the mouse for [MAX_DIFF_MOVE_MOUSE], then hide the status bar
 pub const MAX_DIFF_MOVE_MOUSE: i64 = 1000 * 5; // 5 seconds
+/// open the next file before the end of current file, for gapless playback
+pub const GAPLESS_PREOPEN_MS: i64 = 1000 * 3;
+/// the max zoom of the picture
+pub const MAX_ZOOM: f32 = 16.;
+/// if no frame is played after seeking for [SEEK_TIMEOUT_MS], the seek is completed anyway
+pub const SEEK_TIMEOUT_MS: i64 = 1000 * 3;
+/// the time of showing a notice on the player, eg: the screenshot is saved
+pub const NOTICE_MS: i64 = 1000 * 3;
+/// the width and height of the picture for the music file without the cover art
+pub const AUDIO_ONLY_SIZE: u32 = 480;
@@ -0,0 +1,165 @@
+/// the background behind the transparent picture
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum AlphaBackground {
+    #[default]
+    Checkerboard,
+    Black,
+    White,
+    Gray,
+}
+
+impl AlphaBackground {
+    pub const ALL: [AlphaBackground; 4] = [
+        AlphaBackground::Checkerboard,
+        AlphaBackground::Black,
+        AlphaBackground::White,
+        AlphaBackground::Gray,
+    ];
+    /// the size of a checkerboard square on the screen
+    const SQUARE_SIZE: f32 = 12.;
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlphaBackground::Checkerboard => "Checkerboard",
+            AlphaBackground::Black => "Black",
+            AlphaBackground::White => "White",
+            AlphaBackground::Gray => "Gray",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|b| b.name() == name)
+    }
+
+    /// the 2x2 image of the checkerboard, it is repeated by the uv
+    pub fn checkerboard_image() -> egui::ColorImage {
+        let (light, dark) = (egui::Color32::from_gray(204), egui::Color32::from_gray(153));
+        egui::ColorImage::new([2, 2], vec![light, dark, dark, light])
+    }
+
+    /// the shape to paint in the rect, the checkerboard texture must be loaded with [egui::TextureOptions::NEAREST_REPEAT]
+    pub fn shape(&self, rect: egui::Rect, checkerboard: egui::TextureId) -> egui::Shape {
+        let color = match self {
+            AlphaBackground::Checkerboard => {
+                let uv = egui::Rect::from_min_size(egui::Pos2::ZERO, rect.size() / (2. * Self::SQUARE_SIZE));
+                return egui::Shape::image(checkerboard, rect, uv, egui::Color32::WHITE);
+            }
+            AlphaBackground::Black => egui::Color32::BLACK,
+            AlphaBackground::White => egui::Color32::WHITE,
+            AlphaBackground::Gray => egui::Color32::GRAY,
+        };
+        egui::Shape::rect_filled(rect, 0., color)
+    }
+}
+
+/// how to show the picture in the player area
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum DisplayMode {
+    /// keep the aspect, show the whole picture
+    #[default]
+    Fit,
+    /// keep the aspect, fill the area and crop the picture
+    Fill,
+    /// fill the area, do not keep the aspect
+    Stretch,
+    Aspect4x3,
+    Aspect16x9,
+    Aspect235x1,
+    /// crop the black bars which are detected by the filter "cropdetect"
+    CropBlackBars,
+}
+
+impl DisplayMode {
+    pub const ALL: [DisplayMode; 7] = [
+        DisplayMode::Fit,
+        DisplayMode::Fill,
+        DisplayMode::Stretch,
+        DisplayMode::Aspect4x3,
+        DisplayMode::Aspect16x9,
+        DisplayMode::Aspect235x1,
+        DisplayMode::CropBlackBars,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DisplayMode::Fit => "Fit",
+            DisplayMode::Fill => "Fill",
+            DisplayMode::Stretch => "Stretch",
+            DisplayMode::Aspect4x3 => "4:3",
+            DisplayMode::Aspect16x9 => "16:9",
+            DisplayMode::Aspect235x1 => "2.35:1",
+            DisplayMode::CropBlackBars => "Crop Black Bars",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.name() == name)
+    }
+
+    pub fn next(&self) -> Self {
+        let i = Self::ALL.iter().position(|m| m == self).unwrap_or_default();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// the forced aspect(width / height), none if the aspect of the video is used
+    pub fn aspect(&self) -> Option<f32> {
+        match self {
+            DisplayMode::Aspect4x3 => Some(4. / 3.),
+            DisplayMode::Aspect16x9 => Some(16. / 9.),
+            DisplayMode::Aspect235x1 => Some(2.35),
+            _ => None,
+        }
+    }
+
+    /// the size of the picture and the uv rect of the texture.
+    /// the crop is the uv rect of the picture without black bars, it is used by [DisplayMode::CropBlackBars]
+    pub fn layout(&self, video_size: egui::Vec2, ui_size: egui::Vec2, crop: Option<egui::Rect>) -> (egui::Vec2, egui::Rect) {
+        let full_uv = egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1., 1.));
+        if ui_size.x <= 0.0 || ui_size.y <= 0.0 || video_size.x <= 0.0 || video_size.y <= 0.0 {
+            return (egui::Vec2::ZERO, full_uv);
+        }
+        match self {
+            DisplayMode::Fit => (Self::fit_size(video_size, ui_size), full_uv),
+            DisplayMode::Fill => {
+                // crop the picture to the aspect of the area
+                let video_aspect = video_size.x / video_size.y;
+                let ui_aspect = ui_size.x / ui_size.y;
+                let uv_size = if video_aspect > ui_aspect {
+                    egui::vec2(ui_aspect / video_aspect, 1.)
+                } else {
+                    egui::vec2(1., video_aspect / ui_aspect)
+                };
+                (ui_size, egui::Rect::from_center_size(egui::pos2(0.5, 0.5), uv_size))
+            }
+            DisplayMode::Stretch => (ui_size, full_uv),
+            DisplayMode::Aspect4x3 | DisplayMode::Aspect16x9 | DisplayMode::Aspect235x1 => {
+                let aspect = self.aspect().unwrap_or(1.);
+                (Self::fit_size(egui::vec2(aspect, 1.), ui_size), full_uv)
+            }
+            DisplayMode::CropBlackBars => match crop {
+                Some(crop) if crop.width() > 0. && crop.height() > 0. => (Self::fit_size(video_size * crop.size(), ui_size), crop.intersect(full_uv)),
+                _ => (Self::fit_size(video_size, ui_size), full_uv),
+            },
+        }
+    }
+
+    /// the max size in the area, which keeps the aspect of the video
+    pub fn fit_size(video_size: egui::Vec2, ui_size: egui::Vec2) -> egui::Vec2 {
+        let mut re = egui::Vec2::splat(0.0);
+        if ui_size.x > 0.0 && ui_size.y > 0.0 && video_size.x > 0.0 && video_size.y > 0.0 {
+            let x_ = ui_size.x / video_size.x;
+            let y_ = ui_size.y / video_size.y;
+            if x_ > y_ {
+                re.x = video_size.x * y_;
+                re.y = ui_size.y;
+            } else if x_ == y_ {
+                re.x = ui_size.x;
+                re.y = ui_size.y;
+            } else {
+                re.x = ui_size.x;
+                re.y = video_size.y * x_;
+            }
+        }
+        re
+    }
+}
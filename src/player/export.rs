@@ -0,0 +1,627 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use ffmpeg::Rescale;
+use parking_lot::Mutex;
+
+use crate::{
+    kits::Shared,
+    player::{
+        AV_TIME_BASE_RATIONAL, MILLISECOND_TIME_BASE, Player,
+        kits::{FfmpegKit, data_dir_file, ms_file_name, timestamp_to_millisecond},
+    },
+};
+
+/// an export which runs in a thread, the ui shows its progress and cancels it
+#[derive(Clone)]
+pub struct ExportTask {
+    /// the output file, or the dir of the images
+    pub file: PathBuf,
+    /// 0.0 ~ 1.0
+    progress: Shared<f32>,
+    cancelled: Arc<AtomicBool>,
+    /// none while it is running, the error message if it is failed
+    result: Arc<Mutex<Option<Result<(), String>>>>,
+}
+
+impl ExportTask {
+    /// run the export in the thread, the output file is removed if it is failed or cancelled, but a dir is kept
+    pub fn spawn(file: PathBuf, export: impl FnOnce(&ExportTask) -> Result<(), anyhow::Error> + Send + 'static) -> Self {
+        let task = Self {
+            file,
+            progress: Shared::new(0.),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            result: Arc::new(Mutex::new(None)),
+        };
+        let thread_task = task.clone();
+        let spawned = std::thread::Builder::new().name("export".to_string()).spawn(move || {
+            let re = (|| {
+                if let Some(dir) = thread_task.file.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                export(&thread_task)?;
+                if thread_task.is_cancelled() {
+                    return Err(anyhow::anyhow!("cancelled"));
+                }
+                Ok(())
+            })();
+            let re = match re {
+                Ok(()) => {
+                    log::info!("exported {}", thread_task.file.display());
+                    thread_task.progress.set(1.);
+                    Ok(())
+                }
+                Err(e) => {
+                    log::error!("export {}: {e}", thread_task.file.display());
+                    if thread_task.file.is_file()
+                        && let Err(e) = fs::remove_file(&thread_task.file)
+                    {
+                        log::error!("{e}");
+                    }
+                    Err(e.to_string())
+                }
+            };
+            *thread_task.result.lock() = Some(re);
+        });
+        if let Err(e) = spawned {
+            log::error!("export {}: {e}", task.file.display());
+            *task.result.lock() = Some(Err(e.to_string()));
+        }
+        task
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.progress.get()
+    }
+
+    pub fn set_progress(&self, progress: f32) {
+        self.progress.set(progress.clamp(0., 1.));
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.result.lock().is_some()
+    }
+
+    /// none while it is running
+    pub fn result(&self) -> Option<Result<(), String>> {
+        self.result.lock().clone()
+    }
+}
+
+/// copy the packets of a range to a new file without encoding
+pub struct ClipExport {}
+
+impl ClipExport {
+    pub const DIR_NAME: &'static str = "clips";
+
+    /// the file in the sub dir of the data dir, eg: "movie_00-01-02.000_00-01-30.500.mkv"
+    pub fn file_name(media_path: &str, start_ms: i64, end_ms: i64, extension: &str) -> PathBuf {
+        let stem = Path::new(media_path).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let name = format!("{stem}_{}_{}.{extension}", ms_file_name(start_ms), ms_file_name(end_ms));
+        data_dir_file(Self::DIR_NAME).join(name)
+    }
+
+    /// export the range in the thread, the file is in the same container as the media, or mkv if it can not be written
+    pub fn spawn(media_path: &str, start_ms: i64, end_ms: i64) -> ExportTask {
+        let extension = Path::new(media_path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .filter(|e| Self::has_muxer(e))
+            .unwrap_or_else(|| "mkv".to_string());
+        let file = Self::file_name(media_path, start_ms, end_ms, &extension);
+        let media_path = media_path.to_string();
+        ExportTask::spawn(file, move |task| Self::copy(&media_path, &task.file, start_ms, end_ms, task))
+    }
+
+    /// some formats can be read but not written, eg: "flac" can, but "rmvb" can not
+    fn has_muxer(extension: &str) -> bool {
+        let Ok(name) = std::ffi::CString::new(format!("a.{extension}")) else {
+            return false;
+        };
+        unsafe { !ffmpeg::ffi::av_guess_format(std::ptr::null(), name.as_ptr(), std::ptr::null()).is_null() }
+    }
+
+    /// copy the video, audio and subtitle packets from the key frame at or before the start, to the end.
+    /// the timestamps begin at 0 in the new file
+    pub fn copy(input_file: &str, output_file: &Path, start_ms: i64, end_ms: i64, task: &ExportTask) -> Result<(), anyhow::Error> {
+        if end_ms <= start_ms {
+            return Err(anyhow::anyhow!("empty range {start_ms}ms ~ {end_ms}ms"));
+        }
+        let mut input = ffmpeg::format::input(&Path::new(input_file))?;
+        let mut output = ffmpeg::format::output(output_file)?;
+
+        // the index of the output stream, none if the stream is not copied, eg: the data or the cover
+        let mut stream_mapping = vec![None; input.nb_streams() as usize];
+        let mut time_bases = vec![ffmpeg::Rational(0, 1); input.nb_streams() as usize];
+        // the streams to wait to pass the end, the subtitles are sparse
+        let mut waiting = vec![false; input.nb_streams() as usize];
+        let mut ost_index = 0;
+        for (ist_index, ist) in input.streams().enumerate() {
+            let medium = ist.parameters().medium();
+            if !matches!(medium, ffmpeg::media::Type::Video | ffmpeg::media::Type::Audio | ffmpeg::media::Type::Subtitle)
+                || ist.disposition().contains(ffmpeg::format::stream::Disposition::ATTACHED_PIC)
+            {
+                continue;
+            }
+            stream_mapping[ist_index] = Some(ost_index);
+            time_bases[ist_index] = ist.time_base();
+            waiting[ist_index] = medium != ffmpeg::media::Type::Subtitle;
+            ost_index += 1;
+            let mut ost = output.add_stream(ffmpeg::encoder::find(ffmpeg::codec::Id::None))?;
+            ost.set_parameters(ist.parameters());
+            // the tag of the input container may be invalid in the output one
+            unsafe {
+                (*ost.parameters().as_mut_ptr()).codec_tag = 0;
+            }
+        }
+        let video_index = input
+            .streams()
+            .best(ffmpeg::media::Type::Video)
+            .filter(|s| stream_mapping[s.index()].is_some())
+            .map(|s| s.index());
+        output.set_metadata(input.metadata().to_owned());
+        // the dts of the b-frames are before the key frame, they are negative after the shift
+        let mut options = ffmpeg::Dictionary::new();
+        options.set("avoid_negative_ts", "make_zero");
+        output.write_header_with(options)?;
+
+        let ts = start_ms.rescale(MILLISECOND_TIME_BASE, AV_TIME_BASE_RATIONAL);
+        input.seek(ts, ..ts)?;
+        // the start of the clip, it is the key frame of the video
+        let mut offset_ms = None;
+        for (stream, mut packet) in input.packets() {
+            if task.is_cancelled() {
+                return Ok(());
+            }
+            let ist_index = stream.index();
+            let Some(ost_index) = stream_mapping[ist_index] else {
+                continue;
+            };
+            let time_base = time_bases[ist_index];
+            let Some(ts) = packet.pts().or(packet.dts()) else {
+                continue;
+            };
+            let ms = timestamp_to_millisecond(ts, time_base);
+            let offset = match offset_ms {
+                Some(offset) => offset,
+                None => {
+                    // the clip begins at the key frame, the packets before it can not be decoded
+                    if video_index.is_some_and(|i| i != ist_index) || !packet.is_key() {
+                        continue;
+                    }
+                    offset_ms = Some(ms);
+                    ms
+                }
+            };
+            if ms < offset {
+                continue;
+            }
+            // the packets are in the decode order, the b-frames before the end come after the later frames, so end a stream by the dts
+            let dts_ms = packet.dts().map_or(ms, |t| timestamp_to_millisecond(t, time_base));
+            if dts_ms > end_ms {
+                waiting[ist_index] = false;
+                if !waiting.contains(&true) {
+                    break;
+                }
+                continue;
+            }
+            task.set_progress((ms - offset) as f32 / (end_ms - offset).max(1) as f32);
+
+            let shift = offset.rescale(MILLISECOND_TIME_BASE, time_base);
+            packet.set_pts(packet.pts().map(|t| t - shift));
+            packet.set_dts(packet.dts().map(|t| t - shift));
+            packet.rescale_ts(time_base, output.stream(ost_index).ok_or(ffmpeg::Error::StreamNotFound)?.time_base());
+            packet.set_position(-1);
+            packet.set_stream(ost_index);
+            packet.write_interleaved(&mut output)?;
+        }
+        if offset_ms.is_none() {
+            return Err(anyhow::anyhow!("no key frame after {start_ms}ms"));
+        }
+        output.write_trailer()?;
+        Ok(())
+    }
+}
+
+/// the target of the re-encoded export
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum ExportPreset {
+    #[default]
+    Mp4,
+    WebM,
+    Gif,
+}
+
+impl ExportPreset {
+    pub const ALL: [ExportPreset; 3] = [ExportPreset::Mp4, ExportPreset::WebM, ExportPreset::Gif];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportPreset::Mp4 => "MP4 (H.264/AAC)",
+            ExportPreset::WebM => "WebM (VP9/Opus)",
+            ExportPreset::Gif => "GIF",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name() == name)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportPreset::Mp4 => "mp4",
+            ExportPreset::WebM => "webm",
+            ExportPreset::Gif => "gif",
+        }
+    }
+
+    /// the filters after the filters of the player. the pixels are scaled to square, and the size is even for yuv420p.
+    /// the gif is scaled down to 480 width at 15 fps, with the palette of the clip
+    fn video_spec(&self) -> &'static str {
+        match self {
+            ExportPreset::Mp4 | ExportPreset::WebM => "scale=w=trunc(iw*sar/2)*2:h=trunc(ih/2)*2,setsar=1,format=yuv420p",
+            ExportPreset::Gif => "fps=15,scale=w='min(480,iw*sar)':h=-2:flags=lanczos,setsar=1,split[a][b];[a]palettegen[p];[b][p]paletteuse",
+        }
+    }
+
+    fn pixel_format(&self) -> ffmpeg::format::Pixel {
+        match self {
+            ExportPreset::Mp4 | ExportPreset::WebM => ffmpeg::format::Pixel::YUV420P,
+            ExportPreset::Gif => ffmpeg::format::Pixel::PAL8,
+        }
+    }
+
+    /// the external encoders are better than the native ones
+    fn video_encoder(&self) -> Option<ffmpeg::Codec> {
+        match self {
+            ExportPreset::Mp4 => ffmpeg::encoder::find_by_name("libx264").or_else(|| ffmpeg::encoder::find(ffmpeg::codec::Id::H264)),
+            ExportPreset::WebM => ffmpeg::encoder::find_by_name("libvpx-vp9").or_else(|| ffmpeg::encoder::find(ffmpeg::codec::Id::VP9)),
+            ExportPreset::Gif => ffmpeg::encoder::find(ffmpeg::codec::Id::GIF),
+        }
+    }
+
+    fn video_options(&self) -> ffmpeg::Dictionary<'static> {
+        let mut options = ffmpeg::Dictionary::new();
+        match self {
+            ExportPreset::Mp4 => {
+                options.set("preset", "veryfast");
+                options.set("crf", "20");
+            }
+            ExportPreset::WebM => {
+                // constant quality, the bitrate is not limited
+                options.set("crf", "32");
+                options.set("b", "0");
+                options.set("deadline", "good");
+                options.set("cpu-used", "4");
+                options.set("row-mt", "1");
+            }
+            ExportPreset::Gif => {}
+        }
+        options
+    }
+
+    /// none if the preset has no audio
+    fn audio_encoder(&self) -> Option<ffmpeg::Codec> {
+        match self {
+            ExportPreset::Mp4 => ffmpeg::encoder::find(ffmpeg::codec::Id::AAC),
+            ExportPreset::WebM => ffmpeg::encoder::find_by_name("libopus").or_else(|| ffmpeg::encoder::find(ffmpeg::codec::Id::OPUS)),
+            ExportPreset::Gif => None,
+        }
+    }
+
+    fn audio_options(&self) -> ffmpeg::Dictionary<'static> {
+        let mut options = ffmpeg::Dictionary::new();
+        // the native opus encoder is experimental
+        options.set("strict", "experimental");
+        options
+    }
+}
+
+/// decode a stream, filter the frames and encode the ones in the range
+struct Transcoder {
+    index: usize,
+    ost_index: usize,
+    decoder: ffmpeg::decoder::Opened,
+    graph: ffmpeg::filter::Graph,
+    encoder: ffmpeg::encoder::Encoder,
+    /// the time base of the filter output and the encoder
+    time_base: ffmpeg::Rational,
+    /// the range in the time base
+    start: i64,
+    end: i64,
+    /// the end in the time base of the stream, the frames after it are not filtered
+    decode_end: i64,
+    /// a frame after the end is decoded or filtered, the packets after it are useless
+    finished: bool,
+}
+
+impl Transcoder {
+    const AUDIO_RATE: i32 = 48000;
+
+    fn video(
+        input: &ffmpeg::format::context::Input,
+        output: &mut ffmpeg::format::context::Output,
+        filter_spec: &str,
+        preset: ExportPreset,
+        range_ms: (i64, i64),
+    ) -> Result<Self, anyhow::Error> {
+        let stream = FfmpegKit::video_stream(input).ok_or(ffmpeg::Error::StreamNotFound)?;
+        let context = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?;
+        let decoder = FfmpegKit::video_decoder(context, &stream)?;
+        let spec = [filter_spec, preset.video_spec()]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut graph = Player::graph(&decoder, &spec, stream.time_base())?;
+
+        let mut sink = graph.get("out").ok_or(ffmpeg::Error::FilterNotFound)?;
+        let time_base = sink.sink().time_base();
+        let (width, height, aspect, frame_rate) = unsafe {
+            let ptr = sink.as_ptr();
+            (
+                ffmpeg::ffi::av_buffersink_get_w(ptr) as u32,
+                ffmpeg::ffi::av_buffersink_get_h(ptr) as u32,
+                ffmpeg::Rational::from(ffmpeg::ffi::av_buffersink_get_sample_aspect_ratio(ptr)),
+                ffmpeg::Rational::from(ffmpeg::ffi::av_buffersink_get_frame_rate(ptr)),
+            )
+        };
+
+        let codec = preset.video_encoder().ok_or(ffmpeg::Error::EncoderNotFound)?;
+        let mut encoder = ffmpeg::codec::context::Context::new_with_codec(codec).encoder().video()?;
+        encoder.set_width(width);
+        encoder.set_height(height);
+        encoder.set_aspect_ratio(aspect);
+        encoder.set_format(preset.pixel_format());
+        encoder.set_time_base(time_base);
+        encoder.set_frame_rate((frame_rate.numerator() > 0).then_some(frame_rate));
+        if output.format().flags().contains(ffmpeg::format::Flags::GLOBAL_HEADER) {
+            encoder.set_flags(ffmpeg::codec::Flags::GLOBAL_HEADER);
+        }
+        let encoder = encoder.open_as_with(codec, preset.video_options())?;
+        let mut ost = output.add_stream(codec)?;
+        ost.set_parameters(&encoder);
+        ost.set_time_base(time_base);
+        Ok(Self::new(&stream, ost.index(), decoder.0, graph, encoder.0.0, time_base, range_ms))
+    }
+
+    /// none if there is no audio or the preset has no audio
+    fn audio(
+        input: &ffmpeg::format::context::Input,
+        output: &mut ffmpeg::format::context::Output,
+        preset: ExportPreset,
+        range_ms: (i64, i64),
+    ) -> Result<Option<Self>, anyhow::Error> {
+        let (Some(stream), Some(codec)) = (input.streams().best(ffmpeg::media::Type::Audio), preset.audio_encoder()) else {
+            return Ok(None);
+        };
+        let context = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?;
+        let decoder = context.decoder().audio()?;
+        let format = codec
+            .audio()
+            .ok()
+            .and_then(|a| a.formats())
+            .and_then(|mut formats| formats.next())
+            .unwrap_or(ffmpeg::format::Sample::F32(ffmpeg::format::sample::Type::Planar));
+
+        let mut encoder = ffmpeg::codec::context::Context::new_with_codec(codec).encoder().audio()?;
+        encoder.set_rate(Self::AUDIO_RATE);
+        encoder.set_channel_layout(ffmpeg::ChannelLayout::default(2));
+        encoder.set_format(format);
+        encoder.set_bit_rate(160_000);
+        encoder.set_time_base((1, Self::AUDIO_RATE));
+        if output.format().flags().contains(ffmpeg::format::Flags::GLOBAL_HEADER) {
+            encoder.set_flags(ffmpeg::codec::Flags::GLOBAL_HEADER);
+        }
+        let encoder = encoder.open_as_with(codec, preset.audio_options())?;
+
+        let mut graph = ffmpeg::filter::Graph::new();
+        let args = format!(
+            "time_base={}/{}:sample_rate={}:sample_fmt={}:channels={}",
+            stream.time_base().numerator(),
+            stream.time_base().denominator(),
+            decoder.rate(),
+            decoder.format().name(),
+            decoder.channels()
+        );
+        graph.add(&ffmpeg::filter::find("abuffer").ok_or(ffmpeg::Error::FilterNotFound)?, "in", &args)?;
+        graph.add(&ffmpeg::filter::find("abuffersink").ok_or(ffmpeg::Error::FilterNotFound)?, "out", "")?;
+        let spec = format!("aresample={},aformat=sample_fmts={}:channel_layouts=stereo", Self::AUDIO_RATE, format.name());
+        graph.output("in", 0)?.input("out", 0)?.parse(&spec)?;
+        graph.validate()?;
+        if !codec.capabilities().contains(ffmpeg::codec::capabilities::Capabilities::VARIABLE_FRAME_SIZE) {
+            graph.get("out").expect("").sink().set_frame_size(encoder.frame_size());
+        }
+
+        let mut ost = output.add_stream(codec)?;
+        ost.set_parameters(&encoder);
+        ost.set_time_base((1, Self::AUDIO_RATE));
+        Ok(Some(Self::new(
+            &stream,
+            ost.index(),
+            decoder.0,
+            graph,
+            encoder.0.0,
+            (1, Self::AUDIO_RATE).into(),
+            range_ms,
+        )))
+    }
+
+    fn new(
+        stream: &ffmpeg::Stream,
+        ost_index: usize,
+        decoder: ffmpeg::decoder::Opened,
+        graph: ffmpeg::filter::Graph,
+        encoder: ffmpeg::encoder::Encoder,
+        time_base: ffmpeg::Rational,
+        (start_ms, end_ms): (i64, i64),
+    ) -> Self {
+        Self {
+            index: stream.index(),
+            ost_index,
+            decoder,
+            graph,
+            encoder,
+            time_base,
+            start: start_ms.rescale(MILLISECOND_TIME_BASE, time_base),
+            end: end_ms.rescale(MILLISECOND_TIME_BASE, time_base),
+            decode_end: end_ms.rescale(MILLISECOND_TIME_BASE, stream.time_base()),
+            finished: false,
+        }
+    }
+
+    fn send_packet(&mut self, packet: &ffmpeg::Packet, output: &mut ffmpeg::format::context::Output) -> Result<(), anyhow::Error> {
+        if let Err(e) = self.decoder.send_packet(packet) {
+            // skip the broken packet
+            log::error!("export decode: {e}");
+            return Ok(());
+        }
+        self.receive_frames(output)
+    }
+
+    fn receive_frames(&mut self, output: &mut ffmpeg::format::context::Output) -> Result<(), anyhow::Error> {
+        let mut frame = unsafe { ffmpeg::Frame::empty() };
+        while self.decoder.receive_frame(&mut frame).is_ok() {
+            // some filters output nothing until the eof, eg: palettegen, so stop before the filters and flush them
+            if frame.timestamp().is_some_and(|ts| ts > self.decode_end) {
+                self.finished = true;
+                break;
+            }
+            frame.set_pts(frame.timestamp());
+            self.graph.get("in").expect("").source().add(&frame)?;
+            self.receive_filtered(output)?;
+        }
+        Ok(())
+    }
+
+    fn receive_filtered(&mut self, output: &mut ffmpeg::format::context::Output) -> Result<(), anyhow::Error> {
+        let mut frame = unsafe { ffmpeg::Frame::empty() };
+        while self.graph.get("out").expect("").sink().frame(&mut frame).is_ok() {
+            let Some(pts) = frame.pts() else {
+                continue;
+            };
+            if pts < self.start {
+                continue;
+            }
+            if pts > self.end {
+                self.finished = true;
+                continue;
+            }
+            frame.set_pts(Some(pts - self.start));
+            // the encoder decides the picture types
+            unsafe { (*frame.as_mut_ptr()).pict_type = ffmpeg::ffi::AVPictureType::AV_PICTURE_TYPE_NONE };
+            self.encoder.send_frame(&frame)?;
+            self.receive_packets(output)?;
+        }
+        Ok(())
+    }
+
+    fn receive_packets(&mut self, output: &mut ffmpeg::format::context::Output) -> Result<(), anyhow::Error> {
+        let ost_time_base = output.stream(self.ost_index).ok_or(ffmpeg::Error::StreamNotFound)?.time_base();
+        let mut packet = ffmpeg::Packet::empty();
+        while self.encoder.receive_packet(&mut packet).is_ok() {
+            packet.set_stream(self.ost_index);
+            packet.rescale_ts(self.time_base, ost_time_base);
+            packet.write_interleaved(output)?;
+        }
+        Ok(())
+    }
+
+    /// drain the decoder, the filters and the encoder
+    fn finish(&mut self, output: &mut ffmpeg::format::context::Output) -> Result<(), anyhow::Error> {
+        if !self.finished {
+            self.decoder.send_eof()?;
+            self.receive_frames(output)?;
+        }
+        self.graph.get("in").expect("").source().flush()?;
+        self.receive_filtered(output)?;
+        self.encoder.send_eof()?;
+        self.receive_packets(output)
+    }
+}
+
+/// decode a range, filter it like the player and encode it by a preset
+pub struct EncodeExport {}
+
+impl EncodeExport {
+    /// the file in the dir of [ClipExport], eg: "movie_00-01-02.000_00-01-30.500_enc.mp4"
+    pub fn file_name(media_path: &str, start_ms: i64, end_ms: i64, preset: ExportPreset) -> PathBuf {
+        let stem = Path::new(media_path).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let name = format!("{stem}_{}_{}_enc.{}", ms_file_name(start_ms), ms_file_name(end_ms), preset.extension());
+        data_dir_file(ClipExport::DIR_NAME).join(name)
+    }
+
+    /// export the range in the thread, see [EncodeExport::encode]
+    pub fn spawn(media_path: &str, start_ms: i64, end_ms: i64, filter_spec: &str, preset: ExportPreset) -> ExportTask {
+        let file = Self::file_name(media_path, start_ms, end_ms, preset);
+        let media_path = media_path.to_string();
+        let filter_spec = filter_spec.to_string();
+        ExportTask::spawn(file, move |task| {
+            Self::encode(&media_path, &task.file, start_ms, end_ms, &filter_spec, preset, task)
+        })
+    }
+
+    /// encode the video and the audio from the start to the end. the frames are filtered by the filter spec,
+    /// eg: [Player::export_filter_spec], and the timestamps begin at 0 in the new file
+    pub fn encode(
+        input_file: &str,
+        output_file: &Path,
+        start_ms: i64,
+        end_ms: i64,
+        filter_spec: &str,
+        preset: ExportPreset,
+        task: &ExportTask,
+    ) -> Result<(), anyhow::Error> {
+        if end_ms <= start_ms {
+            return Err(anyhow::anyhow!("empty range {start_ms}ms ~ {end_ms}ms"));
+        }
+        let mut input = ffmpeg::format::input(&Path::new(input_file))?;
+        let mut output = ffmpeg::format::output(output_file)?;
+        let mut transcoders = vec![Transcoder::video(&input, &mut output, filter_spec, preset, (start_ms, end_ms))?];
+        transcoders.extend(Transcoder::audio(&input, &mut output, preset, (start_ms, end_ms))?);
+        output.set_metadata(input.metadata().to_owned());
+        output.write_header()?;
+
+        let video_time_base = input.stream(transcoders[0].index).ok_or(ffmpeg::Error::StreamNotFound)?.time_base();
+        let ts = start_ms.rescale(MILLISECOND_TIME_BASE, AV_TIME_BASE_RATIONAL);
+        input.seek(ts, ..ts)?;
+        for (stream, packet) in input.packets() {
+            if task.is_cancelled() {
+                return Ok(());
+            }
+            let Some(transcoder) = transcoders.iter_mut().find(|t| t.index == stream.index() && !t.finished) else {
+                continue;
+            };
+            transcoder.send_packet(&packet, &mut output)?;
+            if stream.index() == transcoders[0].index
+                && let Some(ts) = packet.pts()
+            {
+                // the packets from the key frame before the start are pre-rolled
+                let ms = timestamp_to_millisecond(ts, video_time_base).clamp(start_ms, end_ms);
+                task.set_progress((ms - start_ms) as f32 / (end_ms - start_ms) as f32);
+            }
+            if transcoders.iter().all(|t| t.finished) {
+                break;
+            }
+        }
+        for transcoder in &mut transcoders {
+            transcoder.finish(&mut output)?;
+        }
+        output.write_trailer()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use std::sync::Arc;
+
+use ffmpeg::software::scaling;
+
+use crate::player::kits::FfmpegKit;
+
+/// the reusable rgba images. an image is reused when the pool holds the only reference,
+/// that is, the play thread and the texture manager have dropped it
+pub struct ImagePool {
+    images: Vec<Arc<egui::ColorImage>>,
+    capacity: usize,
+}
+
+impl ImagePool {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            images: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// a free image of the size, it is a new one if there is no free image
+    fn acquire(&mut self, size: [usize; 2]) -> Arc<egui::ColorImage> {
+        // the free images of other sizes are useless, eg: the filters are changed
+        self.images.retain(|image| image.size == size || Arc::strong_count(image) > 1);
+        if let Some(i) = self.images.iter().position(|image| Arc::strong_count(image) == 1) {
+            return self.images.swap_remove(i);
+        }
+        Arc::new(egui::ColorImage::filled(size, egui::Color32::TRANSPARENT))
+    }
+
+    /// keep the image to reuse it, after it is sent to the play thread
+    fn release(&mut self, image: &Arc<egui::ColorImage>) {
+        if self.images.len() < self.capacity {
+            self.images.push(image.clone());
+        }
+    }
+}
+
+/// the input of the cached scaler, the scaler is created again if it is changed
+#[derive(PartialEq, Eq, Clone, Copy)]
+struct ScalerKey {
+    format: ffmpeg::format::Pixel,
+    width: u32,
+    height: u32,
+    out_width: u32,
+    out_height: u32,
+    space: ffmpeg::color::Space,
+    range: ffmpeg::color::Range,
+}
+
+/// sws is slower if the output is not aligned to it
+const SWS_ALIGN: usize = 16;
+
+/// convert the frames to the rgba images of egui. sws writes to the pixels of the image directly if they are aligned,
+/// or else to the aligned frame, then the pixels are copied(and premultiplied) from it
+pub struct FrameConverter {
+    scaler: Option<(ScalerKey, scaling::Context)>,
+    /// the aligned rgba frame
+    scratch: Option<ffmpeg::frame::Video>,
+    pool: ImagePool,
+}
+
+impl FrameConverter {
+    pub fn new(pool_capacity: usize) -> Self {
+        Self {
+            scaler: None,
+            scratch: None,
+            pool: ImagePool::new(pool_capacity),
+        }
+    }
+
+    fn scaler<'a>(
+        scaler: &'a mut Option<(ScalerKey, scaling::Context)>,
+        frame: &ffmpeg::frame::Video,
+        out_width: u32,
+        out_height: u32,
+    ) -> Result<&'a mut scaling::Context, ffmpeg::Error> {
+        let key = ScalerKey {
+            format: frame.format(),
+            width: frame.width(),
+            height: frame.height(),
+            out_width,
+            out_height,
+            space: frame.color_space(),
+            range: frame.color_range(),
+        };
+        if scaler.as_ref().is_none_or(|(k, _)| *k != key) {
+            let mut context = scaling::Context::get(
+                key.format,
+                key.width,
+                key.height,
+                ffmpeg::format::Pixel::RGBA,
+                out_width,
+                out_height,
+                scaling::Flags::BILINEAR | scaling::Flags::ACCURATE_RND | scaling::Flags::FULL_CHR_H_INT,
+            )?;
+            FfmpegKit::set_sws_colorspace(&mut context, frame);
+            *scaler = Some((key, context));
+        }
+        Ok(&mut scaler.as_mut().expect("").1)
+    }
+
+    fn scale(scaler: &mut scaling::Context, frame: &ffmpeg::frame::Video, dst: *mut u8, dst_linesize: i32) -> Result<(), ffmpeg::Error> {
+        let dst_data = [dst, std::ptr::null_mut(), std::ptr::null_mut(), std::ptr::null_mut()];
+        let dst_linesize = [dst_linesize, 0, 0, 0];
+        let re = unsafe {
+            ffmpeg::ffi::sws_scale(
+                scaler.as_mut_ptr(),
+                (*frame.as_ptr()).data.as_ptr() as *const *const _,
+                (*frame.as_ptr()).linesize.as_ptr(),
+                0,
+                frame.height() as i32,
+                dst_data.as_ptr(),
+                dst_linesize.as_ptr(),
+            )
+        };
+        if re < 0 { Err(ffmpeg::Error::from(re)) } else { Ok(()) }
+    }
+
+    /// the size of the frame scaled by the scale(0.0 ~ 1.0), it is even for the chroma subsampling
+    pub fn scaled_size(frame: &ffmpeg::frame::Video, scale: f32) -> (u32, u32) {
+        if scale >= 1. {
+            return (frame.width(), frame.height());
+        }
+        let scaled = |v: u32| (((v as f32 * scale) as u32) & !1).clamp(2.min(v), v);
+        (scaled(frame.width()), scaled(frame.height()))
+    }
+
+    /// convert the frame and scale it to width x height. the alpha channel is kept if the frame has it
+    pub fn convert(&mut self, frame: &ffmpeg::frame::Video, width: u32, height: u32) -> Result<Arc<egui::ColorImage>, ffmpeg::Error> {
+        if frame.width() == 0 || frame.height() == 0 || width == 0 || height == 0 {
+            return Err(ffmpeg::Error::InvalidData);
+        }
+        let mut image = self.pool.acquire([width as usize, height as usize]);
+        {
+            let alpha = FfmpegKit::has_alpha(frame.format());
+            let scaler = Self::scaler(&mut self.scaler, frame, width, height)?;
+            let dst = Arc::get_mut(&mut image).ok_or(ffmpeg::Error::Bug)?.as_raw_mut();
+            let row_len = width as usize * 4;
+            if !alpha && dst.as_ptr() as usize % SWS_ALIGN == 0 && row_len % SWS_ALIGN == 0 {
+                Self::scale(scaler, frame, dst.as_mut_ptr(), row_len as i32)?;
+            } else {
+                if self.scratch.as_ref().is_none_or(|s| s.width() != width || s.height() != height) {
+                    self.scratch = Some(ffmpeg::frame::Video::new(ffmpeg::format::Pixel::RGBA, width, height));
+                }
+                let scratch = self.scratch.as_mut().expect("");
+                let stride = scratch.stride(0);
+                Self::scale(scaler, frame, scratch.data_mut(0).as_mut_ptr(), stride as i32)?;
+                for (src, dst) in scratch.data(0).chunks(stride).zip(dst.chunks_exact_mut(row_len)) {
+                    let src = &src[..row_len];
+                    if alpha {
+                        // the pixels of egui are premultiplied, sws does not do it
+                        for (d, s) in dst.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+                            d.copy_from_slice(&egui::Color32::from_rgba_unmultiplied(s[0], s[1], s[2], s[3]).to_array());
+                        }
+                    } else {
+                        dst.copy_from_slice(src);
+                    }
+                }
+            }
+        }
+        self.pool.release(&image);
+        Ok(image)
+    }
+}
@@ -0,0 +1,301 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ffmpeg::Rescale;
+
+use crate::player::{
+    AV_TIME_BASE_RATIONAL, ExportTask, FrameConverter, MILLISECOND_TIME_BASE, Player, Screenshot, ScreenshotFormat,
+    kits::{FfmpegKit, data_dir_file, ms_file_name, timestamp_to_millisecond},
+};
+
+/// seek to the next sample if it is farther than this, or else decode to it
+const SAMPLE_SEEK_MIN_MS: i64 = 5000;
+/// width of the frames in the contact sheet
+const SHEET_CELL_WIDTH: u32 = 320;
+const SHEET_COLUMNS: usize = 4;
+const SHEET_MARGIN: usize = 8;
+/// the frames after it are not in the contact sheet
+const SHEET_MAX_FRAMES: usize = 200;
+/// the pixel size of the glyph dot
+const GLYPH_SCALE: usize = 3;
+
+/// which frames are sampled in the range
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum FrameSampling {
+    /// every n ms
+    Interval(i64),
+    KeyFrames,
+}
+
+/// how the sampled frames are saved
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum FramesOutput {
+    /// numbered png files in a dir
+    #[default]
+    Images,
+    /// one png with the frames in a grid, and their timestamps
+    ContactSheet,
+}
+
+impl FramesOutput {
+    pub const ALL: [FramesOutput; 2] = [FramesOutput::Images, FramesOutput::ContactSheet];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FramesOutput::Images => "Images",
+            FramesOutput::ContactSheet => "Contact Sheet",
+        }
+    }
+}
+
+/// sample the frames of a range, they are filtered like the player
+pub struct FramesExport {}
+
+impl FramesExport {
+    pub const DIR_NAME: &'static str = "frames";
+
+    /// the dir of the images or the file of the contact sheet, in the sub dir of the data dir.
+    /// eg: "movie_00-01-02.000_00-01-30.500" or "movie_00-01-02.000_00-01-30.500_sheet.png"
+    pub fn file_name(media_path: &str, start_ms: i64, end_ms: i64, output: FramesOutput) -> PathBuf {
+        let stem = Path::new(media_path).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let name = format!("{stem}_{}_{}", ms_file_name(start_ms), ms_file_name(end_ms));
+        let name = match output {
+            FramesOutput::Images => name,
+            FramesOutput::ContactSheet => format!("{name}_sheet.{}", ScreenshotFormat::Png.extension()),
+        };
+        data_dir_file(Self::DIR_NAME).join(name)
+    }
+
+    /// export the frames in the thread
+    pub fn spawn(media_path: &str, start_ms: i64, end_ms: i64, filter_spec: &str, sampling: FrameSampling, output: FramesOutput) -> ExportTask {
+        let file = Self::file_name(media_path, start_ms, end_ms, output);
+        let media_path = media_path.to_string();
+        let filter_spec = filter_spec.to_string();
+        ExportTask::spawn(file, move |task| match output {
+            FramesOutput::Images => Self::export_images(&media_path, &task.file, start_ms, end_ms, &filter_spec, sampling, task),
+            FramesOutput::ContactSheet => Self::export_contact_sheet(&media_path, &task.file, start_ms, end_ms, &filter_spec, sampling, task),
+        })
+    }
+
+    /// save the frames as "0001_00-01-02.000.png" and so on in the dir
+    pub fn export_images(
+        input_file: &str,
+        dir: &Path,
+        start_ms: i64,
+        end_ms: i64,
+        filter_spec: &str,
+        sampling: FrameSampling,
+        task: &ExportTask,
+    ) -> Result<(), anyhow::Error> {
+        fs::create_dir_all(dir)?;
+        let mut count = 0;
+        Self::sample(input_file, start_ms, end_ms, filter_spec, sampling, task, |ms, frame| {
+            count += 1;
+            let image = Player::frame_to_color_image(frame)?;
+            Screenshot::save(&image, &dir.join(format!("{count:04}_{}.png", ms_file_name(ms))), ScreenshotFormat::Png)?;
+            Ok(true)
+        })
+    }
+
+    /// save the frames in a grid with their timestamps, at most [SHEET_MAX_FRAMES]
+    pub fn export_contact_sheet(
+        input_file: &str,
+        file: &Path,
+        start_ms: i64,
+        end_ms: i64,
+        filter_spec: &str,
+        sampling: FrameSampling,
+        task: &ExportTask,
+    ) -> Result<(), anyhow::Error> {
+        let mut converter = FrameConverter::new(0);
+        let mut cells = Vec::new();
+        Self::sample(input_file, start_ms, end_ms, filter_spec, sampling, task, |ms, frame| {
+            if cells.len() >= SHEET_MAX_FRAMES {
+                log::info!("contact sheet: only the first {SHEET_MAX_FRAMES} frames");
+                return Ok(false);
+            }
+            // the anamorphic video is scaled to the square pixels
+            let aspect = frame.aspect_ratio();
+            let sample_aspect = if aspect.numerator() > 0 && aspect.denominator() > 0 {
+                f64::from(aspect) as f32
+            } else {
+                1.
+            };
+            let display_width = frame.width() as f32 * sample_aspect;
+            let width = SHEET_CELL_WIDTH.min(display_width as u32).max(1);
+            let height = ((frame.height() as f32 * width as f32 / display_width.max(1.)) as u32).max(1);
+            cells.push((ms, Arc::unwrap_or_clone(converter.convert(frame, width, height)?)));
+            Ok(true)
+        })?;
+        if task.is_cancelled() {
+            return Ok(());
+        }
+        if cells.is_empty() {
+            return Err(anyhow::anyhow!("no frame in {start_ms}ms ~ {end_ms}ms"));
+        }
+        Screenshot::save(&Self::contact_sheet(&cells), file, ScreenshotFormat::Png)
+    }
+
+    /// decode the range and call `on_frame` with the sampled frames and their ms, until it returns false.
+    /// the frames are filtered by the filter spec, eg: [Player::export_filter_spec]
+    pub fn sample(
+        input_file: &str,
+        start_ms: i64,
+        end_ms: i64,
+        filter_spec: &str,
+        sampling: FrameSampling,
+        task: &ExportTask,
+        mut on_frame: impl FnMut(i64, &ffmpeg::frame::Video) -> Result<bool, anyhow::Error>,
+    ) -> Result<(), anyhow::Error> {
+        let mut input = ffmpeg::format::input(&Path::new(input_file))?;
+        let (video_index, time_base, mut decoder) = {
+            let stream = FfmpegKit::video_stream(&input).ok_or(ffmpeg::Error::StreamNotFound)?;
+            let context = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?;
+            (stream.index(), stream.time_base(), FfmpegKit::video_decoder(context, &stream)?)
+        };
+        let mut target_ms = start_ms;
+        let stateful = FfmpegKit::has_stateful_filter(filter_spec);
+        let mut graph = None;
+        'SEEK: loop {
+            let ts = target_ms.rescale(MILLISECOND_TIME_BASE, AV_TIME_BASE_RATIONAL);
+            input.seek(ts, ..ts)?;
+            decoder.flush();
+            // the graph is kept if it can be, eg: the filter "subtitles" parses the whole file when it is built
+            if !filter_spec.is_empty() && (graph.is_none() || stateful) {
+                graph = Some(Player::graph(&decoder, filter_spec, time_base)?);
+            }
+            let mut frame = ffmpeg::frame::Video::empty();
+            for (stream, packet) in input.packets() {
+                if task.is_cancelled() {
+                    return Ok(());
+                }
+                if stream.index() != video_index || (sampling == FrameSampling::KeyFrames && !packet.is_key()) {
+                    continue;
+                }
+                if let Err(e) = decoder.send_packet(&packet) {
+                    log::error!("sample frames: {e}");
+                    continue;
+                }
+                let mut frames = Vec::new();
+                while decoder.receive_frame(&mut frame).is_ok() {
+                    frame.set_pts(frame.timestamp());
+                    let Some(graph) = &mut graph else {
+                        frames.push(std::mem::replace(&mut frame, ffmpeg::frame::Video::empty()));
+                        continue;
+                    };
+                    graph.get("in").expect("").source().add(&frame)?;
+                    let mut filter_frame = ffmpeg::frame::Video::empty();
+                    while graph.get("out").expect("").sink().frame(&mut filter_frame).is_ok() {
+                        frames.push(std::mem::replace(&mut filter_frame, ffmpeg::frame::Video::empty()));
+                    }
+                }
+                for f in frames {
+                    let Some(pts) = f.pts() else {
+                        continue;
+                    };
+                    let ms = timestamp_to_millisecond(pts, time_base);
+                    if ms > end_ms {
+                        return Ok(());
+                    }
+                    if ms < target_ms {
+                        continue;
+                    }
+                    task.set_progress((ms - start_ms) as f32 / (end_ms - start_ms).max(1) as f32);
+                    if !on_frame(ms, &f)? {
+                        return Ok(());
+                    }
+                    if let FrameSampling::Interval(interval) = sampling {
+                        let interval = interval.max(1);
+                        target_ms += ((ms - target_ms) / interval + 1) * interval;
+                        if target_ms - ms > SAMPLE_SEEK_MIN_MS {
+                            continue 'SEEK;
+                        }
+                    }
+                }
+            }
+            return Ok(());
+        }
+    }
+
+    /// the frames in a grid, the timestamp is under each frame
+    pub fn contact_sheet(cells: &[(i64, egui::ColorImage)]) -> egui::ColorImage {
+        let cell_width = cells.iter().map(|(_, image)| image.width()).max().unwrap_or_default();
+        let cell_height = cells.iter().map(|(_, image)| image.height()).max().unwrap_or_default();
+        let label_height = GLYPH_SCALE * 5 + SHEET_MARGIN;
+        let columns = SHEET_COLUMNS.min(cells.len()).max(1);
+        let rows = cells.len().div_ceil(columns);
+        let width = columns * (cell_width + SHEET_MARGIN) + SHEET_MARGIN;
+        let height = rows * (cell_height + label_height + SHEET_MARGIN) + SHEET_MARGIN;
+        let mut sheet = egui::ColorImage::filled([width, height], egui::Color32::from_gray(24));
+        for (i, (ms, image)) in cells.iter().enumerate() {
+            let x = SHEET_MARGIN + i % columns * (cell_width + SHEET_MARGIN);
+            let y = SHEET_MARGIN + i / columns * (cell_height + label_height + SHEET_MARGIN);
+            for (row, pixels) in image.pixels.chunks_exact(image.width()).enumerate() {
+                let start = (y + row) * width + x;
+                sheet.pixels[start..start + pixels.len()].copy_from_slice(pixels);
+            }
+            let text = Player::format_duration(chrono::Duration::milliseconds(*ms));
+            Self::draw_text(&mut sheet, &text, x, y + cell_height + SHEET_MARGIN / 2, egui::Color32::WHITE);
+        }
+        sheet
+    }
+
+    /// draw the digits and ':' by a 3x5 bitmap font, the other chars are skipped
+    fn draw_text(image: &mut egui::ColorImage, text: &str, x: usize, y: usize, color: egui::Color32) {
+        let width = image.width();
+        for (i, c) in text.chars().enumerate() {
+            let glyph: [u8; 5] = match c {
+                '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+                '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+                '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+                '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+                '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+                '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+                '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+                '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+                '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+                '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+                ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+                _ => continue,
+            };
+            // 3 dots and 1 space
+            let glyph_x = x + i * 4 * GLYPH_SCALE;
+            for (row, bits) in glyph.into_iter().enumerate() {
+                for col in 0..3 {
+                    if bits & (0b100 >> col) == 0 {
+                        continue;
+                    }
+                    for dy in 0..GLYPH_SCALE {
+                        let px = glyph_x + col * GLYPH_SCALE;
+                        let py = y + row * GLYPH_SCALE + dy;
+                        if px + GLYPH_SCALE <= width && py < image.height() {
+                            image.pixels[py * width + px..py * width + px + GLYPH_SCALE].fill(color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_contact_sheet() {
+        let cells: Vec<_> = (0..5).map(|i| (i * 61_000, egui::ColorImage::filled([32, 18], egui::Color32::RED))).collect();
+        let sheet = FramesExport::contact_sheet(&cells);
+        let label_height = GLYPH_SCALE * 5 + SHEET_MARGIN;
+        assert_eq!(sheet.width(), 4 * (32 + SHEET_MARGIN) + SHEET_MARGIN);
+        assert_eq!(sheet.height(), 2 * (18 + label_height + SHEET_MARGIN) + SHEET_MARGIN);
+        // the frame of the second row, and the top left dot of "00:00"
+        let (x, y) = (SHEET_MARGIN, SHEET_MARGIN + 18 + label_height + SHEET_MARGIN);
+        assert_eq!(sheet.pixels[y * sheet.width() + x], egui::Color32::RED);
+        let label_y = SHEET_MARGIN + 18 + SHEET_MARGIN / 2;
+        assert_eq!(sheet.pixels[label_y * sheet.width() + SHEET_MARGIN], egui::Color32::WHITE);
+    }
+}
@@ -1,9 +1,10 @@
 use std::{
+    cmp::Ordering,
     collections::HashSet,
-    ffi::{CStr, c_void},
+    ffi::{CStr, c_int, c_void},
     fs, path,
     path::PathBuf,
-    sync::Arc,
+    sync::{Arc, OnceLock},
 };
 
 use ffmpeg::{Rational, Rescale};
@@ -22,27 +23,248 @@ pub fn timestamp_to_millisecond(timestamp: i64, time_base: Rational) -> i64 {
     timestamp.rescale(time_base, MILLISECOND_TIME_BASE)
 }
 
+/// the name of the dir in the data dir of the platform
+pub const APP_ID: &str = "Door Player";
+
+/// the file in the data dir of the platform, eg: "~/.local/share/doorplayer" on linux.
+/// it is beside the exe if there is no data dir or it can not be created. it is used to save the settings and the exported files
+pub fn data_dir_file(name: &str) -> PathBuf {
+    static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+    DATA_DIR
+        .get_or_init(|| match eframe::storage_dir(APP_ID) {
+            Some(dir) => match fs::create_dir_all(&dir) {
+                Ok(_) => dir,
+                Err(e) => {
+                    log::error!("{}: {e}", dir.display());
+                    exe_dir()
+                }
+            },
+            None => exe_dir(),
+        })
+        .join(name)
+}
+
+fn exe_dir() -> PathBuf {
+    match std::env::current_exe() {
+        Ok(exe) => exe.parent().map(PathBuf::from).unwrap_or_default(),
+        Err(e) => {
+            log::error!("{e}");
+            PathBuf::new()
+        }
+    }
+}
+
+/// the time as a part of the file name, eg: "01-02-03.456"
+pub fn ms_file_name(ms: i64) -> String {
+    let ms = ms.max(0);
+    format!("{:02}-{:02}-{:02}.{:03}", ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60, ms % 1000)
+}
+
+/// compare the strings in natural(human) order, "ep2" < "ep10", ignore case
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                fn take_number(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+                    let mut number = String::new();
+                    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+                        number.push(c);
+                    }
+                    number
+                }
+                let x_number = take_number(&mut a_chars);
+                let y_number = take_number(&mut b_chars);
+                let x_number = x_number.trim_start_matches('0');
+                let y_number = y_number.trim_start_matches('0');
+                let re = x_number.len().cmp(&y_number.len()).then_with(|| x_number.cmp(y_number));
+                if re != Ordering::Equal {
+                    return re;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let re = x.to_lowercase().cmp(y.to_lowercase());
+                if re != Ordering::Equal {
+                    return re;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
 pub struct FfmpegKit {}
 
 impl FfmpegKit {
-    pub fn demuxers() -> Vec<String> {
-        let mut names = Vec::with_capacity(512);
+    /// they can be demuxed by ffmpeg, but they are not media to play
+    pub const NON_MEDIA_EXTENSIONS: [&'static str; 34] = [
+        "srt", "ass", "ssa", "sub", "smi", "vtt", "sup", "idx", "lrc", "txt", "nfo", "diz", "asc", "json", "xml", "ffmeta", "m3u8", "jpg", "jpeg", "png",
+        "gif", "bmp", "tif", "tiff", "webp", "psd", "svg", "ico", "jxl", "pgm", "ppm", "pbm", "dpx", "exr",
+    ];
 
-        let mut opaque: *mut c_void = std::ptr::null_mut();
-        let mut input_format: *const ffmpeg::ffi::AVInputFormat;
+    /// the filters which keep the frames before a seek, the graph is built again after the seek if it has one of them
+    pub const STATEFUL_FILTERS: [&'static str; 21] = [
+        "yadif",
+        "bwdif",
+        "w3fdif",
+        "estdif",
+        "nnedi",
+        "kerndeint",
+        "hqdn3d",
+        "atadenoise",
+        "tmix",
+        "tmedian",
+        "tblend",
+        "minterpolate",
+        "fps",
+        "framerate",
+        "deflicker",
+        "mpdecimate",
+        "decimate",
+        "fieldmatch",
+        "pullup",
+        "deshake",
+        "cropdetect",
+    ];
+
+    /// the filter spec has a filter in [FfmpegKit::STATEFUL_FILTERS]
+    pub fn has_stateful_filter(filter_spec: &str) -> bool {
+        filter_spec.split([',', ';']).any(|f| {
+            // the labels and the instance name are skipped, eg: "[a]yadif@deint=mode=1[b]" -> "yadif"
+            let mut f = f.trim();
+            while let Some(rest) = f.strip_prefix('[') {
+                f = rest.split_once(']').map_or("", |(_, r)| r).trim_start();
+            }
+            let name = f.split(['=', '[', '@']).next().unwrap_or_default().trim();
+            Self::STATEFUL_FILTERS.contains(&name)
+        })
+    }
+
+    /// the file extensions which ffmpeg can demux, exclude subtitles, images and so on
+    pub fn media_extensions() -> &'static [String] {
+        static EXTENSIONS: OnceLock<Vec<String>> = OnceLock::new();
+        EXTENSIONS.get_or_init(|| {
+            let mut names: HashSet<String> = Self::demuxer_names(false).chain(Self::demuxer_names(true)).map(|n| n.to_lowercase()).collect();
+            names.insert("mkv".to_string());
+            let mut names: Vec<String> = names.into_iter().filter(|n| !Self::NON_MEDIA_EXTENSIONS.contains(&n.as_str())).collect();
+            names.sort();
+            names
+        })
+    }
+
+    /// set the yuv matrix and the range of the frame to the scaler, the output rgb is full range.
+    /// the unspecified matrix is guessed by the height: bt709 for hd, bt601 for sd
+    pub fn set_sws_colorspace(context: &mut ffmpeg::software::scaling::Context, frame: &ffmpeg::frame::Video) {
+        let space = match frame.color_space() {
+            ffmpeg::color::Space::Unspecified | ffmpeg::color::Space::Reserved if frame.height() >= 720 => ffmpeg::color::Space::BT709,
+            ffmpeg::color::Space::Unspecified | ffmpeg::color::Space::Reserved => ffmpeg::color::Space::BT470BG,
+            s => s,
+        };
+        let src_range = (frame.color_range() == ffmpeg::color::Range::JPEG) as c_int;
         unsafe {
-            loop {
-                input_format = ffmpeg::ffi::av_demuxer_iterate(&mut opaque as _);
-                if input_format.is_null() {
-                    break;
-                }
-                if !(*input_format).name.is_null() {
-                    let name = CStr::from_ptr((*input_format).name).to_str().expect("");
-                    let ns: Vec<_> = name.split(',').map(|s| s.to_string()).collect();
-                    names.extend(ns);
+            // the values of AVColorSpace are the same as SWS_CS_*
+            let coefficients = ffmpeg::ffi::sws_getCoefficients(ffmpeg::ffi::AVColorSpace::from(space) as c_int);
+            // -1 if the input is not yuv, keep the default
+            ffmpeg::ffi::sws_setColorspaceDetails(context.as_mut_ptr(), coefficients, src_range, coefficients, 1, 0, 1 << 16, 1 << 16);
+        }
+    }
+
+    /// the pixel format has the alpha channel, eg: yuva420p, rgba
+    pub fn has_alpha(format: ffmpeg::format::Pixel) -> bool {
+        format
+            .descriptor()
+            .is_some_and(|d| unsafe { (*d.as_ptr()).flags & ffmpeg::ffi::AV_PIX_FMT_FLAG_ALPHA as u64 != 0 })
+    }
+
+    /// the sample(pixel) aspect ratio of the video stream, from the container or the codec. 1.0 if it is unknown
+    pub fn sample_aspect(input: &ffmpeg::format::context::Input, stream: &ffmpeg::Stream) -> f32 {
+        let sar: Rational =
+            unsafe { ffmpeg::ffi::av_guess_sample_aspect_ratio(input.as_ptr() as *mut _, stream.as_ptr() as *mut _, std::ptr::null_mut()) }.into();
+        if sar.numerator() > 0 && sar.denominator() > 0 {
+            f64::from(sar) as f32
+        } else {
+            1.
+        }
+    }
+
+    /// the video stream to play, the attached picture(cover art of the music file) is not a video
+    pub fn video_stream(input: &ffmpeg::format::context::Input) -> Option<ffmpeg::Stream<'_>> {
+        let is_video = |s: &ffmpeg::Stream| {
+            s.parameters().medium() == ffmpeg::media::Type::Video && !s.disposition().contains(ffmpeg::format::stream::Disposition::ATTACHED_PIC)
+        };
+        input
+            .streams()
+            .best(ffmpeg::media::Type::Video)
+            .filter(is_video)
+            .or_else(|| input.streams().find(is_video))
+    }
+
+    /// decode the attached picture(cover art), none if there is no one
+    pub fn cover_art(input: &ffmpeg::format::context::Input) -> Option<ffmpeg::frame::Video> {
+        let stream = input
+            .streams()
+            .find(|s| s.disposition().contains(ffmpeg::format::stream::Disposition::ATTACHED_PIC))?;
+        let decode = || -> Result<ffmpeg::frame::Video, anyhow::Error> {
+            let packet = unsafe {
+                let pic = &(*stream.as_ptr()).attached_pic;
+                if pic.data.is_null() || pic.size <= 0 {
+                    return Err(ffmpeg::Error::InvalidData.into());
                 }
+                ffmpeg::Packet::copy(std::slice::from_raw_parts(pic.data, pic.size as usize))
+            };
+            let context = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?;
+            let mut decoder = context.decoder().video()?;
+            decoder.send_packet(&packet)?;
+            decoder.send_eof()?;
+            let mut frame = ffmpeg::frame::Video::empty();
+            decoder.receive_frame(&mut frame)?;
+            Ok(frame)
+        };
+        match decode() {
+            Ok(frame) => Some(frame),
+            Err(e) => {
+                log::error!("cover art: {e}");
+                None
             }
         }
+    }
+
+    /// open the decoder of the video stream. the native vp9 decoder drops the alpha channel of webm, use libvpx-vp9 if it is there
+    pub fn video_decoder(context: ffmpeg::codec::context::Context, stream: &ffmpeg::Stream) -> Result<ffmpeg::decoder::Video, ffmpeg::Error> {
+        if stream.parameters().id() == ffmpeg::codec::Id::VP9
+            && stream.metadata().get("alpha_mode") == Some("1")
+            && let Some(codec) = ffmpeg::decoder::find_by_name("libvpx-vp9")
+        {
+            return context.decoder().open_as(codec)?.video();
+        }
+        context.decoder().video()
+    }
+
+    pub fn is_media_file(file: &path::Path) -> bool {
+        file.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| Self::media_extensions().contains(&e.to_lowercase()))
+    }
+
+    /// the names(or the extensions) of all demuxers, the comma separated ones are split, eg: "matroska,webm"
+    fn demuxer_names(extensions: bool) -> impl Iterator<Item = String> {
+        let mut opaque: *mut c_void = std::ptr::null_mut();
+        std::iter::from_fn(move || unsafe { ffmpeg::ffi::av_demuxer_iterate(&mut opaque as _).as_ref() })
+            .map(move |f| if extensions { f.extensions } else { f.name })
+            .filter(|p| !p.is_null())
+            .flat_map(|p| {
+                let names = unsafe { CStr::from_ptr(p) }.to_string_lossy();
+                names.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect::<Vec<_>>()
+            })
+    }
+
+    pub fn demuxers() -> Vec<String> {
+        let mut names: Vec<String> = Self::demuxer_names(false).collect();
         if !names.contains(&"mkv".to_string()) {
             names.push("mkv".to_string());
         }
@@ -129,6 +351,10 @@ impl SubTitle {
 
 #[cfg(test)]
 mod test {
+    use std::cmp::Ordering;
+
+    use super::{FfmpegKit, natural_cmp};
+
     #[test]
     fn test_for() {
         let len = 2;
@@ -136,4 +362,77 @@ mod test {
             println!("{}", i);
         }
     }
+
+    #[test]
+    fn test_natural_cmp() {
+        assert_eq!(natural_cmp("ep2.mkv", "ep10.mkv"), Ordering::Less);
+        assert_eq!(natural_cmp("Ep10.mkv", "ep9.mkv"), Ordering::Greater);
+        assert_eq!(natural_cmp("a.mkv", "B.mkv"), Ordering::Less);
+        assert_ne!(natural_cmp("ep02", "ep2"), Ordering::Equal);
+        assert_eq!(natural_cmp("ep1", "ep1 part"), Ordering::Less);
+        let mut files = vec!["ep10", "ep1", "ep2", "Ep3"];
+        files.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(files, ["ep1", "ep2", "Ep3", "ep10"]);
+    }
+
+    #[test]
+    fn test_has_stateful_filter() {
+        assert!(!FfmpegKit::has_stateful_filter(""));
+        assert!(!FfmpegKit::has_stateful_filter("eq=contrast=1.2,subtitles=filename='a.srt'"));
+        assert!(FfmpegKit::has_stateful_filter("hflip,yadif=mode=1"));
+        assert!(FfmpegKit::has_stateful_filter("[in]bwdif@deint[out]"));
+    }
+
+    #[test]
+    fn test_media_extensions() {
+        let extensions = FfmpegKit::media_extensions();
+        assert!(extensions.iter().any(|e| e == "mkv"));
+        for e in ["m3u8", "gif", "txt"] {
+            assert!(!extensions.iter().any(|x| x == e), "{e} is not a media file");
+        }
+    }
+
+    /// bt709 limited range bars: white, yellow, cyan, green, magenta, red, blue, black
+    #[test]
+    fn test_color_bars() {
+        let bars: [([u8; 3], [u8; 3]); 8] = [
+            ([235, 128, 128], [255, 255, 255]),
+            ([219, 16, 138], [255, 255, 0]),
+            ([188, 154, 16], [0, 255, 255]),
+            ([173, 42, 26], [0, 255, 0]),
+            ([78, 214, 230], [255, 0, 255]),
+            ([63, 102, 240], [255, 0, 0]),
+            ([32, 240, 118], [0, 0, 255]),
+            ([16, 128, 128], [0, 0, 0]),
+        ];
+        let bar_width = 16;
+        let (width, height) = (bar_width * bars.len(), 8);
+        let mut frame = ffmpeg::frame::Video::new(ffmpeg::format::Pixel::YUV444P, width as u32, height as u32);
+        frame.set_color_space(ffmpeg::color::Space::BT709);
+        frame.set_color_range(ffmpeg::color::Range::MPEG);
+        for plane in 0..3 {
+            let stride = frame.stride(plane);
+            let data = frame.data_mut(plane);
+            for y in 0..height {
+                for x in 0..width {
+                    data[y * stride + x] = bars[x / bar_width].0[plane];
+                }
+            }
+        }
+        let image = crate::player::Player::frame_to_color_image(&frame).unwrap();
+        for (i, (_, rgb)) in bars.iter().enumerate() {
+            let c = image.pixels[(height / 2) * width + i * bar_width + bar_width / 2];
+            for (v, e) in [c.r(), c.g(), c.b()].into_iter().zip(rgb) {
+                assert!(v.abs_diff(*e) <= 3, "bar {i}: {c:?} != {rgb:?}");
+            }
+        }
+
+        // full range, the limited white is gray
+        frame.set_color_range(ffmpeg::color::Range::JPEG);
+        for (plane, v) in [235u8, 128, 128].into_iter().enumerate() {
+            frame.data_mut(plane).fill(v);
+        }
+        let image = crate::player::Player::frame_to_color_image(&frame).unwrap();
+        assert!(image.pixels.iter().all(|c| [c.r(), c.g(), c.b()].iter().all(|v| v.abs_diff(235) <= 3)));
+    }
 }
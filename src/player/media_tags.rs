@@ -0,0 +1,86 @@
+use std::path::Path;
+
+/// the tags of the music file, they are shown by the audio-only layout
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MediaTags {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    /// eg: "3" or "3/12"
+    pub track: String,
+}
+
+impl MediaTags {
+    /// the tags of the container, the missing ones are from the audio stream, eg: the vorbis comments of ogg
+    pub fn from_input(input: &ffmpeg::format::context::Input) -> Self {
+        let mut tags = Self::from_metadata(&input.metadata());
+        if let Some(stream) = input.streams().best(ffmpeg::media::Type::Audio) {
+            let stream_tags = Self::from_metadata(&stream.metadata());
+            for (tag, stream_tag) in [
+                (&mut tags.title, stream_tags.title),
+                (&mut tags.artist, stream_tags.artist),
+                (&mut tags.album, stream_tags.album),
+                (&mut tags.track, stream_tags.track),
+            ] {
+                if tag.is_empty() {
+                    *tag = stream_tag;
+                }
+            }
+        }
+        tags
+    }
+
+    /// the keys are case insensitive
+    pub fn from_metadata(metadata: &ffmpeg::DictionaryRef) -> Self {
+        let get = |keys: &[&str]| {
+            keys.iter()
+                .filter_map(|k| metadata.get(k))
+                .map(|v| v.trim())
+                .find(|v| !v.is_empty())
+                .unwrap_or_default()
+                .to_string()
+        };
+        Self {
+            title: get(&["title"]),
+            artist: get(&["artist", "album_artist", "performer"]),
+            album: get(&["album"]),
+            track: get(&["track"]),
+        }
+    }
+
+    /// the title, or the file name without the extension
+    pub fn title_or_file(&self, file: &str) -> String {
+        if self.title.is_empty() {
+            Path::new(file).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()
+        } else {
+            self.title.clone()
+        }
+    }
+
+    /// the album and the track in one line, eg: "Album · 3/12"
+    pub fn album_line(&self) -> String {
+        [self.album.as_str(), self.track.as_str()]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(" · ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MediaTags;
+
+    #[test]
+    fn test_from_metadata() {
+        let mut metadata = ffmpeg::Dictionary::new();
+        metadata.set("TITLE", "Song");
+        metadata.set("album_artist", "Band");
+        metadata.set("track", "3/12");
+        let tags = MediaTags::from_metadata(&metadata);
+        assert_eq!(tags.title, "Song");
+        assert_eq!(tags.artist, "Band");
+        assert_eq!(tags.album_line(), "3/12");
+        assert_eq!(MediaTags::default().title_or_file("/music/a song.flac"), "a song");
+    }
+}
@@ -1,20 +1,48 @@
 pub use audio::*;
+pub use chapter::*;
 pub use clock::*;
+pub use color_adjust::*;
 pub use command::*;
 pub use consts::*;
+pub use display_mode::*;
+pub use export::*;
+pub use frame_converter::*;
+pub use frames_export::*;
+pub use media_tags::*;
 pub use play_ctrl::*;
 pub use player_::*;
+pub use playlist::*;
+pub use screenshot::*;
+pub use settings::*;
 pub use subtitle::*;
+pub use thumbnail::*;
+pub use tone_map::*;
 pub use video::*;
 pub use video_audio_rs::*;
+pub use video_filter::*;
+pub use watch_history::*;
 
 mod audio;
+mod chapter;
 mod clock;
+mod color_adjust;
 mod command;
 mod consts;
+mod display_mode;
+mod export;
+mod frame_converter;
+mod frames_export;
 pub mod kits;
+mod media_tags;
 mod play_ctrl;
 mod player_;
+mod playlist;
+mod screenshot;
+mod settings;
 mod subtitle;
+mod thumbnail;
+mod tone_map;
 mod video;
 mod video_audio_rs;
+mod video_filter;
+mod watch_history;
@@ -1,18 +1,19 @@
 use std::{
     sync::{
         Arc,
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
     },
     time::Duration,
 };
 
 use bytemuck::NoUninit;
+use parking_lot::Mutex;
 use ringbuf::traits::{Observer, Producer};
 
 use crate::{
     kits::{Shared, TextureHandleNoMut},
     player::{
-        AV_TIME_BASE_RATIONAL, Clock, CommandGo, VIDEO_SYNC_THRESHOLD_MIN,
+        AV_TIME_BASE_RATIONAL, Clock, CommandQueue, VIDEO_SYNC_THRESHOLD_MIN,
         audio::{AudioDevice, AudioPlayFrame},
         consts::VIDEO_SYNC_THRESHOLD_MAX,
         kits::{RingBufferProducer, timestamp_to_millisecond},
@@ -50,14 +51,32 @@ pub struct PlayCtrl {
     audio_clock: Arc<Clock>,
     /// The player's texture handle.
     pub texture_handle: TextureHandleNoMut,
-    // producer: Arc<Mutex<RingBufferProducer<f32>>>,
+    /// the producer of the audio device, it is shared by the next player for gapless playback
+    pub audio_producer: Arc<Mutex<RingBufferProducer<f32>>>,
+    /// decode the first packets, but do not play them
+    preroll: Arc<AtomicBool>,
     pub duration: i64,
     pub duration_ms: i64,
     pub video_elapsed_ms: Shared<i64>,
     pub audio_elapsed_ms: Shared<i64>,
     pub video_elapsed_ms_override: Shared<i64>,
+    /// the uv rect without black bars of the playing frame, all zero if it is not detected
+    pub video_crop: Shared<[f32; 4]>,
+    /// the playing frame has the alpha channel
+    pub video_alpha: Shared<bool>,
+    /// the decode thread scales the picture by it, 1.0 is the full resolution
+    pub video_scale: Shared<f32>,
+    /// the pts of the frame which is showing, [ffmpeg::ffi::AV_NOPTS_VALUE] if there is none.
+    /// the frame is decoded again for the screenshot, the queued frames do not keep the source resolution
+    pub playing_pts: Shared<i64>,
+    /// the command id of the latest seek
+    seek_serial: Arc<AtomicU64>,
+    /// the command id of the latest seek done by the read packet thread, frames decoded after it carry it
+    flushed_serial: Arc<AtomicU64>,
+    /// the state before seeking, it is restored after the seek is completed
+    pub pre_seek_state: Shared<PlayerState>,
 
-    pub command_go: Shared<CommandGo>,
+    pub commands: CommandQueue,
     pub video_stream_time_base: Option<ffmpeg::Rational>,
     pub audio_stream_time_base: Option<ffmpeg::Rational>,
 }
@@ -66,6 +85,7 @@ impl PlayCtrl {
     pub fn new(
         duration: i64,
         audio_dev: Arc<AudioDevice>,
+        audio_producer: Arc<Mutex<RingBufferProducer<f32>>>,
         texture_handle: TextureHandleNoMut,
         video_stream_time_base: Option<ffmpeg::Rational>,
         audio_stream_time_base: Option<ffmpeg::Rational>,
@@ -99,12 +119,21 @@ impl PlayCtrl {
             audio_volume: Shared::new(0.5),
             audio_clock,
             texture_handle,
+            audio_producer,
+            preroll: Arc::new(AtomicBool::new(false)),
             duration,
             duration_ms: timestamp_to_millisecond(duration, AV_TIME_BASE_RATIONAL),
             video_elapsed_ms: Shared::new(0),
             audio_elapsed_ms: Shared::new(0),
             video_elapsed_ms_override: Shared::new(-1),
-            command_go: Shared::new(CommandGo::None),
+            video_crop: Shared::new([0.; 4]),
+            video_alpha: Shared::new(false),
+            video_scale: Shared::new(1.),
+            playing_pts: Shared::new(ffmpeg::ffi::AV_NOPTS_VALUE),
+            seek_serial: Arc::new(AtomicU64::new(0)),
+            flushed_serial: Arc::new(AtomicU64::new(0)),
+            pre_seek_state: Shared::new(PlayerState::Playing),
+            commands: CommandQueue::default(),
             video_stream_time_base,
             audio_stream_time_base,
         }
@@ -138,6 +167,71 @@ impl PlayCtrl {
     pub fn packet_finished(&self) -> bool {
         self.packet_finished.load(Ordering::Relaxed)
     }
+    /// all streams are played
+    pub fn play_finished(&self) -> bool {
+        (self.video_stream_time_base.is_none() || self.video_finished()) && (self.audio_stream_time_base.is_none() || self.audio_finished())
+    }
+    /// after seek, the streams are not finished, and play again if it is at the end of file
+    pub fn reset_finished(&self) {
+        self.set_packet_finished(false);
+        self.set_audio_finished(false);
+        self.set_video_finished(false);
+        if self.player_state.get() == PlayerState::EndOfFile {
+            self.player_state.set(PlayerState::Playing);
+        }
+    }
+    pub fn seek_serial(&self) -> u64 {
+        self.seek_serial.load(Ordering::Relaxed)
+    }
+    pub fn flushed_serial(&self) -> u64 {
+        self.flushed_serial.load(Ordering::Relaxed)
+    }
+    pub fn set_flushed_serial(&self, serial: u64) {
+        self.flushed_serial.store(serial, Ordering::Relaxed);
+    }
+    /// enter [PlayerState::Seeking], the serial is the id of the seek command
+    pub fn begin_seek(&self, serial: u64) {
+        self.seek_serial.store(serial, Ordering::Relaxed);
+        match self.player_state.get() {
+            PlayerState::Stopped | PlayerState::Seeking(_) => {}
+            PlayerState::Paused => {
+                self.pre_seek_state.set(PlayerState::Paused);
+                self.player_state.set(PlayerState::Seeking(true));
+            }
+            _ => {
+                self.pre_seek_state.set(PlayerState::Playing);
+                self.player_state.set(PlayerState::Seeking(true));
+            }
+        }
+    }
+    /// the first frame after the latest seek is played
+    pub fn finish_seek(&self, serial: u64) {
+        if self.player_state.get() == PlayerState::Seeking(true) && serial == self.seek_serial() {
+            self.player_state.set(PlayerState::Seeking(false));
+        }
+    }
+    /// seeking from [PlayerState::Paused], it is paused again after the first frame is played
+    pub fn is_paused_seek(&self) -> bool {
+        matches!(self.player_state.get(), PlayerState::Seeking(_)) && self.pre_seek_state.get() == PlayerState::Paused
+    }
+    /// paused, or the first frame after a paused seek is played and the ui has not restored [PlayerState::Paused]
+    pub fn is_paused(&self) -> bool {
+        match self.player_state.get() {
+            PlayerState::Paused => true,
+            PlayerState::Seeking(false) => self.is_paused_seek(),
+            _ => false,
+        }
+    }
+    /// the frame is decoded before the latest seek, it should not be played while seeking
+    pub fn is_stale(&self, serial: u64) -> bool {
+        matches!(self.player_state.get(), PlayerState::Seeking(_)) && serial != self.seek_serial()
+    }
+    pub fn set_preroll(&self, preroll: bool) {
+        self.preroll.store(preroll, Ordering::Relaxed);
+    }
+    pub fn preroll(&self) -> bool {
+        self.preroll.load(Ordering::Relaxed)
+    }
 
     pub fn audio_config(&self) -> cpal::SupportedStreamConfig {
         self.audio_dev.output_config()
@@ -182,7 +276,12 @@ impl PlayCtrl {
 
     pub fn play_video(&self, frame: VideoPlayFrame, ctx: &egui::Context) -> Result<(), anyhow::Error> {
         let delay = self.update_video_clock(frame.pts, frame.duration, frame.timestamp);
+        if let Some(crop) = frame.crop {
+            self.video_crop.set(crop);
+        }
+        self.video_alpha.set(frame.alpha);
         self.texture_handle.set(frame.color_image, egui::TextureOptions::LINEAR);
+        self.playing_pts.set(frame.pts);
         ctx.request_repaint();
         if delay > 0.0 {
             log::debug!("video delay: {delay}");
@@ -1,16 +1,21 @@
-use std::{default::Default, fs, ops::Deref, path, sync::Arc, time::UNIX_EPOCH};
+use std::{cell::RefCell, default::Default, fs, ops::Deref, path, sync::Arc, time::UNIX_EPOCH};
 
 use chrono::{DateTime, Utc};
 use egui::{Ui, Visuals, load::SizedTexture};
 use ffmpeg::software::resampling::Context as ResamplingContext;
+use parking_lot::Mutex;
 use ringbuf::traits::Split;
 
 use crate::{
-    kits::{Shared, TextureHandleNoMut},
+    kits::{Deque, Shared, TextureHandleNoMut, new_deque},
     player::{
-        CommandGo, CommandUi, MAX_DIFF_MOVE_MOUSE, PlayerState, SubtitlePlayFrame, VideoAudioRS,
+        AlphaBackground, Chapter, ClipExport, ColorAdjust, Command, CommandGo, CommandUi, DisplayMode, EncodeExport, ExportPreset, ExportTask, FrameConverter,
+        FrameSampling, FramesExport, FramesOutput, Hdr, MAX_DIFF_MOVE_MOUSE, MAX_ZOOM, MediaTags, PlayerState, SEEK_TIMEOUT_MS, Screenshot, ScreenshotFormat,
+        SubtitlePlayFrame, Thumbnails, ToneMap, VideoAudioRS, VideoFilters, VideoTransform,
         audio::{AudioDevice, AudioPlayFrame},
-        consts::{AUDIO_FRAME_QUEUE_SIZE, AUDIO_PACKET_QUEUE_SIZE, PLAY_MIN_INTERVAL, VIDEO_FRAME_QUEUE_SIZE, VIDEO_PACKET_QUEUE_SIZE},
+        consts::{
+            AUDIO_FRAME_QUEUE_SIZE, AUDIO_ONLY_SIZE, AUDIO_PACKET_QUEUE_SIZE, NOTICE_MS, PLAY_MIN_INTERVAL, VIDEO_FRAME_QUEUE_SIZE, VIDEO_PACKET_QUEUE_SIZE,
+        },
         kits,
         kits::RingBufferProducer,
         play_ctrl::PlayCtrl,
@@ -22,12 +27,22 @@ use crate::{
 pub struct Player {
     //是否需要停止播放相关线程
     pub play_ctrl: Arc<PlayCtrl>,
+    /// the media file
+    file: String,
+    /// the subtitles which are burned by the filter, empty if there is none
+    sub_title_file: String,
     pub width: u32,
     pub height: u32,
+    /// the width / height of a pixel, it is not 1.0 for the anamorphic video
+    pub sample_aspect: f32,
 
     last_seek_ms: Option<i64>,
+    /// the time when the seek begins, see [SEEK_TIMEOUT_MS]
+    seek_start_ts: i64,
     //按一次tab 前进的时间，默认为0
     pub tab_seek_ms: i64,
+    /// play the file again at the end of file
+    pub repeat_current: bool,
 
     /// mouse move ts, compute if show the status bar
     pub mouth_move_ts: i64,
@@ -35,24 +50,110 @@ pub struct Player {
     pub command_ui: Shared<CommandUi>,
 
     pub video_audio_rs: VideoAudioRS,
+
+    /// chapters of the file, sorted by start time
+    pub chapters: Vec<Chapter>,
+    /// the in and out points of the clip to export, see [ClipExport]
+    clip_in_ms: Option<i64>,
+    clip_out_ms: Option<i64>,
+
+    /// thumbnails of the seekbar, none if there is no video stream
+    thumbnails: Option<Thumbnails>,
+
+    /// the new filter chains, the video decode thread rebuilds the graph with the newest one
+    video_filter_requests: Deque<DecodeFilters>,
+    /// the rotation from the stream and the user
+    transform: VideoTransform,
+    /// the filters from [VideoFilters]
+    user_filter_spec: String,
+    /// the error of the newest filters, they are not applied
+    filter_error: Arc<Mutex<Option<String>>>,
+    /// detect the black bars by the filter "cropdetect"
+    crop_detect: bool,
+    /// brightness, contrast and so on
+    color_adjust: ColorAdjust,
+    /// some if the video is hdr, it is tone mapped to sdr
+    hdr: Option<Hdr>,
+    tone_map: ToneMap,
+    /// it is shown behind the transparent video
+    pub alpha_background: AlphaBackground,
+    checkerboard: Option<egui::TextureHandle>,
+    /// 1.0 is not zoomed
+    zoom: f32,
+    /// the offset of the zoomed center in the uv
+    pan: egui::Vec2,
+    /// the message and the time(ms) when it is shown, eg: the screenshot is saved
+    notice: Arc<Mutex<Option<(String, i64)>>>,
+    /// title, artist and so on, they are shown for the music file
+    pub tags: MediaTags,
+    /// the cover art of the music file, it is set to the texture when the player is shown, see [Player::ui]
+    cover: Option<egui::ColorImage>,
 }
 
 impl Player {
     //初始化所有线程，如果之前的还在，结束它们
     pub fn new(ctx: &egui::Context, texture_handle: TextureHandleNoMut, command_ui: Shared<CommandUi>, file: &String) -> Result<Player, anyhow::Error> {
-        {
+        Self::open(ctx, texture_handle, command_ui, file, None)
+    }
+
+    /// open the file to play after `pre` without gap, it shares the audio device and the texture of `pre`.
+    /// the first packets are decoded(pre-roll), but they are not played until [Player::start]
+    pub fn new_next(ctx: &egui::Context, pre: &Player, file: &String) -> Result<Player, anyhow::Error> {
+        let audio = (pre.audio_dev.clone(), pre.audio_producer.clone());
+        Self::open(ctx, pre.texture_handle.clone(), pre.command_ui.clone(), file, Some(audio))
+    }
+
+    fn open(
+        ctx: &egui::Context,
+        texture_handle: TextureHandleNoMut,
+        command_ui: Shared<CommandUi>,
+        file: &String,
+        audio: Option<(Arc<AudioDevice>, Arc<Mutex<RingBufferProducer<f32>>>)>,
+    ) -> Result<Player, anyhow::Error> {
+        let preroll = audio.is_some();
+        let (transform, sample_aspect, tags, cover) = {
             let mut format_input = ffmpeg::format::input(&path::Path::new(file))?;
-            match Self::first_frame(&mut format_input) {
-                Ok(f) => texture_handle.set(Self::frame_to_color_image(&f)?, egui::TextureOptions::LINEAR),
-                Err(e) => log::error!("{e}"),
+            let (transform, sample_aspect, cover) = match kits::FfmpegKit::video_stream(&format_input) {
+                Some(stream) => (
+                    VideoTransform::from_stream(&stream),
+                    kits::FfmpegKit::sample_aspect(&format_input, &stream),
+                    None,
+                ),
+                // the music file, show the cover art instead of the video
+                None => {
+                    let cover = kits::FfmpegKit::cover_art(&format_input)
+                        .and_then(|f| match Self::frame_to_color_image(&f) {
+                            Ok(image) => Some(image),
+                            Err(e) => {
+                                log::error!("cover art: {e}");
+                                None
+                            }
+                        })
+                        .unwrap_or_else(|| {
+                            egui::ColorImage::new(
+                                [AUDIO_ONLY_SIZE as usize; 2],
+                                vec![egui::Color32::from_gray(32); (AUDIO_ONLY_SIZE * AUDIO_ONLY_SIZE) as usize],
+                            )
+                        });
+                    (VideoTransform::default(), 1., Some(cover))
+                }
+            };
+            let tags = MediaTags::from_input(&format_input);
+            // the texture is showing the pre player, do not change it
+            if !preroll && cover.is_none() {
+                match Self::first_frame(&mut format_input) {
+                    Ok(f) => texture_handle.set(transform.apply_image(Self::frame_to_color_image(&f)?), egui::TextureOptions::LINEAR),
+                    Err(e) => log::error!("{e}"),
+                }
             }
             let _ = print_meda_info(&format_input);
-        }
+            (transform, sample_aspect, tags, cover)
+        };
 
         let video_input = ffmpeg::format::input(&path::Path::new(file))?;
         // 获取视频解码器
         let (video_index, video_decoder, video_stream_time_base) = {
-            let video_stream = video_input.streams().best(ffmpeg::media::Type::Video);
+            let video_stream = kits::FfmpegKit::video_stream(&video_input);
             match video_stream {
                 Some(video_stream) => {
                     let video_index = video_stream.index();
@@ -77,7 +178,7 @@ impl Player {
                     }
                     // ffmpeg::codec::Context::new()
 
-                    let video_decoder = video_context.decoder().video()?;
+                    let video_decoder = kits::FfmpegKit::video_decoder(video_context, &video_stream)?;
                     {
                         log::info!("video_stream time base: {}", video_stream.time_base());
                         log::info!("video_decoder time base: {}", video_decoder.time_base());
@@ -127,59 +228,97 @@ impl Player {
         };
 
         // 字幕
-        let graph = {
-            match &video_decoder {
-                None => None,
-                Some(video_decoder) => {
-                    let video_time_base = video_input.stream(video_index).expect("").time_base();
-
-                    let sub_title_file = {
-                        if video_input.streams().best(ffmpeg::media::Type::Subtitle).is_some() {
-                            file.clone()
-                        } else if let Some(f) = kits::SubTitle::sub_files(file).first() {
-                            f.to_str().expect("").to_string()
-                        } else {
-                            "".to_string()
-                        }
-                    };
-
-                    if !sub_title_file.is_empty() {
-                        match Self::graph(video_decoder, &sub_title_file, video_time_base) {
-                            Err(e) => {
-                                log::error!("{e}");
-                                None
-                            }
-                            Ok(t) => Some(t),
-                        }
-                    } else {
-                        None
-                    }
-                }
+        let sub_title_file = {
+            if video_input.streams().best(ffmpeg::media::Type::Subtitle).is_some() {
+                file.clone()
+            } else if let Some(f) = kits::SubTitle::sub_files(file).first() {
+                f.to_str().expect("").to_string()
+            } else {
+                "".to_string()
             }
         };
-        let (producer, consumer) = ringbuf::HeapRb::<f32>::new(8820 * 2).split();
         let mut player = {
             let duration = video_input.duration();
 
             let play_ctrl = {
-                let audio_dev = Arc::new(AudioDevice::new(consumer)?);
-                audio_dev.resume();
-                PlayCtrl::new(duration, audio_dev, texture_handle, video_stream_time_base, audio_stream_time_base)
+                let (audio_dev, audio_producer) = match audio {
+                    Some(t) => t,
+                    None => {
+                        let (producer, consumer) = ringbuf::HeapRb::<f32>::new(8820 * 2).split();
+                        let audio_dev = Arc::new(AudioDevice::new(consumer)?);
+                        audio_dev.resume();
+                        (audio_dev, Arc::new(Mutex::new(producer)))
+                    }
+                };
+                let play_ctrl = PlayCtrl::new(
+                    duration,
+                    audio_dev,
+                    audio_producer,
+                    texture_handle,
+                    video_stream_time_base,
+                    audio_stream_time_base,
+                );
+                play_ctrl.set_preroll(preroll);
+                play_ctrl
             };
             Self {
                 play_ctrl: Arc::new(play_ctrl),
+                file: file.clone(),
+                sub_title_file: sub_title_file.clone(),
                 width: 0,
                 height: 0,
+                sample_aspect: 1.,
                 last_seek_ms: None,
+                seek_start_ts: 0,
                 tab_seek_ms: 0,
+                repeat_current: false,
                 mouth_move_ts: Utc::now().timestamp_millis(),
                 command_ui,
                 video_audio_rs: VideoAudioRS::default(),
+                chapters: Chapter::chapters(&video_input),
+                clip_in_ms: None,
+                clip_out_ms: None,
+                thumbnails: None,
+                video_filter_requests: new_deque(),
+                transform,
+                user_filter_spec: String::new(),
+                filter_error: Arc::new(Mutex::new(None)),
+                crop_detect: false,
+                color_adjust: ColorAdjust::default(),
+                hdr: None,
+                tone_map: ToneMap::default(),
+                alpha_background: AlphaBackground::default(),
+                checkerboard: None,
+                zoom: 1.,
+                pan: egui::Vec2::ZERO,
+                notice: Arc::new(Mutex::new(None)),
+                tags,
+                cover: None,
             }
         };
+        if let Some(cover) = cover {
+            player.width = cover.width() as u32;
+            player.height = cover.height() as u32;
+            player.cover = Some(cover);
+        }
         if let Some(video_decoder) = &video_decoder {
             player.width = video_decoder.width();
             player.height = video_decoder.height();
+            player.sample_aspect = sample_aspect;
+            player.hdr = Hdr::from_decoder(video_decoder);
+            if let Some(hdr) = &player.hdr {
+                log::info!("{} video", hdr.name());
+            }
+            if player.transform != VideoTransform::default() || player.hdr.is_some() {
+                player.apply_video_filters();
+            }
+            player.thumbnails = match Thumbnails::new(ctx, file, player.duration_ms, player.player_state.clone()) {
+                Err(e) => {
+                    log::error!("{e}");
+                    None
+                }
+                Ok(t) => Some(t),
+            };
         }
 
         let video_packet_sender = match video_decoder {
@@ -201,7 +340,8 @@ impl Player {
                     // player.video_stream_time_base = video_stream_time_base;
                 }
                 //run decode video thread
-                player.video_decode_run(video_decoder, video_packet_receiver, video_play_sender, graph);
+                let video_time_base = video_input.stream(video_index).expect("").time_base();
+                player.video_decode_run(video_decoder, video_packet_receiver, video_play_sender, sub_title_file, video_time_base);
                 //run play video thread
                 player.video_play_run(ctx.clone(), video_play_receiver);
 
@@ -223,7 +363,7 @@ impl Player {
                 //run audio decode thread
                 player.audio_decode_run(audio_decoder, audio_packet_receiver, audio_play_sender);
                 //run audio play thread
-                player.audio_play_run(audio_play_receiver, producer);
+                player.audio_play_run(audio_play_receiver);
                 Some(audio_packet_sender)
             }
             None => None,
@@ -257,36 +397,225 @@ impl Player {
     }
 
     pub fn frame_to_color_image(frame: &ffmpeg::frame::Video) -> Result<egui::ColorImage, ffmpeg::Error> {
-        let mut rgb_frame = ffmpeg::frame::Video::empty();
-        let mut context = ffmpeg::software::scaling::Context::get(
-            frame.format(),
-            frame.width(),
-            frame.height(),
-            ffmpeg::format::Pixel::RGB24,
-            frame.width(),
-            frame.height(),
-            ffmpeg::software::scaling::Flags::BILINEAR,
-        )?;
-        context.run(frame, &mut rgb_frame)?;
-
-        let size = [rgb_frame.width() as usize, rgb_frame.height() as usize];
-        let data = rgb_frame.data(0);
-        let stride = rgb_frame.stride(0);
-        let pixel_size_bytes = 3;
-        let byte_width: usize = pixel_size_bytes * rgb_frame.width() as usize;
-        let height: usize = rgb_frame.height() as usize;
-        let mut pixels = Vec::with_capacity(height * rgb_frame.width() as usize / pixel_size_bytes);
-        for line in 0..height {
-            let begin = line * stride;
-            let end = begin + byte_width;
-            let data_line = &data[begin..end];
-            pixels.extend(data_line.chunks_exact(pixel_size_bytes).map(|p| egui::Color32::from_rgb(p[0], p[1], p[2])))
+        Self::frame_to_color_image_size(frame, frame.width(), frame.height())
+    }
+
+    /// convert the frame to image, and scale it to width x height. the alpha channel is kept if the frame has it
+    pub fn frame_to_color_image_size(frame: &ffmpeg::frame::Video, width: u32, height: u32) -> Result<egui::ColorImage, ffmpeg::Error> {
+        thread_local! {
+            /// the scaler is reused by the conversions of the thread, eg: the frames export
+            static CONVERTER: RefCell<FrameConverter> = RefCell::new(FrameConverter::new(0));
+        }
+        let image = CONVERTER.with_borrow_mut(|c| c.convert(frame, width, height))?;
+        Ok(Arc::unwrap_or_clone(image))
+    }
+
+    /// apply the user filters while playing, they are before the subtitles
+    pub fn set_video_filters(&mut self, filters: &VideoFilters) {
+        self.user_filter_spec = filters.spec();
+        self.apply_video_filters();
+    }
+
+    pub fn transform(&self) -> VideoTransform {
+        self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: VideoTransform) {
+        self.transform = transform;
+        self.apply_video_filters();
+    }
+
+    /// rotate 90 degrees
+    pub fn rotate(&mut self, clockwise: bool) {
+        let mut transform = self.transform;
+        transform.rotate(clockwise);
+        self.set_transform(transform);
+        self.refresh_frame();
+    }
+
+    pub fn flip(&mut self, horizontal: bool) {
+        let mut transform = self.transform;
+        if horizontal {
+            transform.hflip = !transform.hflip;
+        } else {
+            transform.vflip = !transform.vflip;
+        }
+        self.set_transform(transform);
+        self.refresh_frame();
+    }
+
+    /// the size of the picture on the screen, the width is scaled by the sample aspect ratio,
+    /// the width and height are swapped if it is rotated 90 degrees
+    pub fn display_size(&self) -> egui::Vec2 {
+        let width = self.width as f32 * self.sample_aspect;
+        if self.transform.is_transposed() {
+            egui::Vec2::new(self.height as f32, width)
+        } else {
+            egui::Vec2::new(width, self.height as f32)
+        }
+    }
+
+    /// the music file without the video, the picture is the cover art
+    pub fn audio_only(&self) -> bool {
+        self.video_stream_time_base.is_none()
+    }
+
+    /// when paused, decode the current frame again to show the new filters
+    pub fn refresh_frame(&mut self) {
+        if self.player_state.get() == PlayerState::Paused && !self.preroll() {
+            self.seek_ms(self.elapsed_ms());
+        }
+    }
+
+    pub fn hdr(&self) -> Option<Hdr> {
+        self.hdr
+    }
+
+    pub fn tone_map(&self) -> ToneMap {
+        self.tone_map
+    }
+
+    /// change the operator of hdr to sdr, nothing is changed for the sdr video
+    pub fn set_tone_map(&mut self, tone_map: ToneMap) {
+        if self.tone_map != tone_map {
+            self.tone_map = tone_map;
+            if self.hdr.is_some() {
+                self.apply_video_filters();
+            }
+        }
+    }
+
+    pub fn color_adjust(&self) -> ColorAdjust {
+        self.color_adjust
+    }
+
+    /// apply the picture adjustments while playing
+    pub fn set_color_adjust(&mut self, mut adjust: ColorAdjust) {
+        adjust.clamp();
+        if self.color_adjust != adjust {
+            self.color_adjust = adjust;
+            self.apply_video_filters();
+        }
+    }
+
+    /// detect the black bars, it is used by [DisplayMode::CropBlackBars]
+    pub fn set_crop_detect(&mut self, crop_detect: bool) {
+        if self.crop_detect != crop_detect {
+            self.crop_detect = crop_detect;
+            self.video_crop.set([0.; 4]);
+            self.apply_video_filters();
+        }
+    }
+
+    /// the uv rect of the picture without black bars, none if it is not detected
+    pub fn crop_uv(&self) -> Option<egui::Rect> {
+        match self.video_crop.get() {
+            [x1, y1, x2, y2] if x2 > x1 && y2 > y1 => Some(egui::Rect::from_min_max(egui::pos2(x1, y1), egui::pos2(x2, y2))),
+            _ => None,
+        }
+    }
+
+    /// the size of the picture and the uv rect of the texture in the area
+    pub fn layout(&self, mode: DisplayMode, ui_size: egui::Vec2) -> (egui::Vec2, egui::Rect) {
+        mode.layout(self.display_size(), ui_size, self.crop_uv())
+    }
+
+    /// read the result of the filter "cropdetect" from the frame metadata
+    fn crop_detected(frame: &ffmpeg::frame::Video) -> Option<[f32; 4]> {
+        let metadata = frame.metadata();
+        let get = |key: &str| metadata.get(key).and_then(|v| v.parse::<f32>().ok());
+        let (x, y, w, h) = (
+            get("lavfi.cropdetect.x")?,
+            get("lavfi.cropdetect.y")?,
+            get("lavfi.cropdetect.w")?,
+            get("lavfi.cropdetect.h")?,
+        );
+        let (width, height) = (frame.width() as f32, frame.height() as f32);
+        if w <= 0. || h <= 0. || width <= 0. || height <= 0. {
+            return None;
+        }
+        Some([x / width, y / height, (x + w) / width, (y + h) / height])
+    }
+
+    /// the error of the newest filters, they are not applied
+    pub fn filter_error(&self) -> Option<String> {
+        self.filter_error.lock().clone()
+    }
+
+    fn apply_video_filters(&self) {
+        let crop_spec = if self.crop_detect { "cropdetect=round=2".to_string() } else { String::new() };
+        let filters = DecodeFilters {
+            spec: self.filter_spec(true, crop_spec.clone(), String::new()),
+            builtin_spec: self.filter_spec(false, crop_spec, String::new()),
+        };
+        self.video_filter_requests.lock().push_back(filters);
+    }
+
+    /// the filters of the export, as the picture is shown: the detected black bars are cropped and the subtitles are burned
+    pub fn export_filter_spec(&self) -> String {
+        let crop_spec = match self.crop_uv().filter(|_| self.crop_detect) {
+            Some(uv) => format!("crop=w=iw*{}:h=ih*{}:x=iw*{}:y=ih*{}", uv.width(), uv.height(), uv.min.x, uv.min.y),
+            None => String::new(),
+        };
+        self.filter_spec(self.filter_error().is_none(), crop_spec, Self::subtitles_spec(&self.sub_title_file))
+    }
+
+    /// the user filters are skipped if `user_filters` is false, eg: they are invalid
+    fn filter_spec(&self, user_filters: bool, crop_spec: String, sub_spec: String) -> String {
+        let tone_map_spec = self.hdr.map(|hdr| self.tone_map.spec(&hdr)).unwrap_or_default();
+        let user_spec = if user_filters { self.user_filter_spec.clone() } else { String::new() };
+        [tone_map_spec, self.transform.spec(), user_spec, crop_spec, self.color_adjust.spec(), sub_spec]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn subtitles_spec(sub_title_file: &str) -> String {
+        if sub_title_file.is_empty() {
+            String::new()
+        } else {
+            format!("subtitles=filename='{sub_title_file}'")
+        }
+    }
+
+    /// the graph of the filters and subtitles, none if there is no filter.
+    /// if the user filters are invalid, the graph is built without them and the error is kept in `filter_error`
+    fn filter_graph(
+        dec_ctx: &ffmpeg::decoder::Video,
+        filters: &DecodeFilters,
+        sub_title_file: &str,
+        time_base: ffmpeg::Rational,
+        filter_error: &Mutex<Option<String>>,
+    ) -> Option<ffmpeg::filter::Graph> {
+        let sub_spec = Self::subtitles_spec(sub_title_file);
+        let with_subtitles = |spec: &str| [spec, &sub_spec].into_iter().filter(|s| !s.is_empty()).collect::<Vec<_>>().join(",");
+        let spec = with_subtitles(&filters.spec);
+        if spec.is_empty() {
+            return None;
+        }
+        match Self::graph(dec_ctx, &spec, time_base) {
+            Ok(t) => Some(t),
+            Err(e) if filters.spec != filters.builtin_spec => {
+                log::error!("filter graph \"{spec}\": {e}");
+                *filter_error.lock() = Some(e.to_string());
+                let spec = with_subtitles(&filters.builtin_spec);
+                if spec.is_empty() {
+                    return None;
+                }
+                Self::graph(dec_ctx, &spec, time_base)
+                    .inspect_err(|e| log::error!("filter graph \"{spec}\": {e}"))
+                    .ok()
+            }
+            Err(e) => {
+                log::error!("filter graph \"{spec}\": {e}");
+                None
+            }
         }
-        Ok(egui::ColorImage::new(size, pixels))
     }
 
     //, time_base_video: ffmpeg::Rational
-    fn graph(dec_ctx: &ffmpeg::decoder::Video, sub_title_file: &str, time_base: ffmpeg::Rational) -> Result<ffmpeg::filter::Graph, ffmpeg::Error> {
+    pub(crate) fn graph(dec_ctx: &ffmpeg::decoder::Video, spec: &str, time_base: ffmpeg::Rational) -> Result<ffmpeg::filter::Graph, ffmpeg::Error> {
         let mut graph = ffmpeg::filter::Graph::new();
         let src = ffmpeg::filter::find("buffer").ok_or(ffmpeg::Error::OptionNotFound)?;
         let sink = ffmpeg::filter::find("buffersink").ok_or(ffmpeg::Error::OptionNotFound)?;
@@ -308,17 +637,16 @@ impl Player {
         parse = parse.output("in", 0)?;
         // let file = ""
         // let spec = format!("subtitles=filename='{}':original_size={}x{}","/home/peace/gopath/src/peacess/door_player/13.mkv",dec_ctx.width(),dec_ctx.height());
-        let spec = format!("subtitles=filename='{sub_title_file}'");
-        parse.parse(&spec)?;
+        parse.parse(spec)?;
         graph.validate()?;
         Ok(graph)
     }
 
     fn first_frame(input: &mut ffmpeg::format::context::Input) -> Result<ffmpeg::frame::Video, anyhow::Error> {
-        let video_stream = input.streams().best(ffmpeg::media::Type::Video).ok_or(ffmpeg::Error::InvalidData)?;
+        let video_stream = kits::FfmpegKit::video_stream(input).ok_or(ffmpeg::Error::InvalidData)?;
         let video_index = video_stream.index();
         let video_context = ffmpeg::codec::context::Context::from_parameters(video_stream.parameters())?;
-        let mut video_decoder = video_context.decoder().video()?;
+        let mut video_decoder = kits::FfmpegKit::video_decoder(video_context, &video_stream)?;
         loop {
             if let Some((_, packet)) = input.packets().next() {
                 if unsafe { packet.is_empty() || packet.stream() != video_index } {
@@ -363,6 +691,7 @@ impl Player {
             }
         };
         let _ = std::thread::Builder::new().name("audio decode".to_string()).spawn(move || {
+            let mut serial = play_ctrl.flushed_serial();
             'RUN: loop {
                 match play_ctrl.player_state.get() {
                     PlayerState::Stopped => {
@@ -371,10 +700,11 @@ impl Player {
                     }
                     PlayerState::EndOfFile => {}
                     PlayerState::Seeking(_) => {}
-                    PlayerState::Paused => {
+                    PlayerState::Paused if !play_ctrl.preroll() => {
                         spin_sleep::sleep(PLAY_MIN_INTERVAL);
                         continue 'RUN;
                     }
+                    PlayerState::Paused => {}
                     PlayerState::Playing => {}
                     PlayerState::Restarting => {}
                 }
@@ -387,10 +717,11 @@ impl Player {
                         }
                         PlayerState::EndOfFile => {}
                         PlayerState::Seeking(_) => {}
-                        PlayerState::Paused => {
+                        PlayerState::Paused if !play_ctrl.preroll() => {
                             spin_sleep::sleep(PLAY_MIN_INTERVAL);
                             continue 'RUN;
                         }
+                        PlayerState::Paused => {}
                         PlayerState::Playing => {}
                         PlayerState::Restarting => {}
                     }
@@ -432,6 +763,7 @@ impl Player {
                                 pts,
                                 duration,
                                 timestamp: frame_old.timestamp().unwrap_or_default(),
+                                serial,
                             };
 
                             if let Err(e) = audio_play_sender.send(audio_frame) {
@@ -465,7 +797,8 @@ impl Player {
                         continue 'RUN;
                     }
                     Ok(Some(None)) => {
-                        //receive all frame
+                        //after seek, receive all frame
+                        serial = play_ctrl.flushed_serial();
                         let mut temp = ffmpeg::frame::Audio::empty();
                         for _ in 0..20 {
                             if audio_decoder.receive_frame(&mut temp).is_err() {
@@ -480,7 +813,7 @@ impl Player {
         });
     }
 
-    fn audio_play_run(&self, audio_play_receiver: kanal::Receiver<AudioPlayFrame>, mut producer: RingBufferProducer<f32>) {
+    fn audio_play_run(&self, audio_play_receiver: kanal::Receiver<AudioPlayFrame>) {
         let play_ctrl = self.play_ctrl.clone();
         let _ = std::thread::Builder::new().name("audio play".to_string()).spawn(move || {
             let mut empty_count = 0;
@@ -489,6 +822,11 @@ impl Player {
                     log::info!("audio play exit");
                     break;
                 }
+                if play_ctrl.preroll() {
+                    // the pre player is using the audio device
+                    spin_sleep::sleep(PLAY_MIN_INTERVAL);
+                    continue;
+                }
                 match audio_play_receiver.try_recv() {
                     Err(e) => {
                         log::error!("{e}");
@@ -503,16 +841,26 @@ impl Player {
                         if play_ctrl.packet_finished() {
                             empty_count += 1;
                             if empty_count == 10 {
+                                // keep the thread, it can play again after seek
                                 play_ctrl.set_audio_finished(true);
-                                log::info!("audio play exit");
-                                break;
+                                log::info!("audio play finished");
                             }
                         }
                     }
                     Ok(Some(frame)) => {
-                        if let Err(e) = play_ctrl.play_audio(frame, &mut producer) {
+                        if play_ctrl.is_stale(frame.serial) {
+                            continue;
+                        }
+                        let serial = frame.serial;
+                        // the audio is not heard while seeking from paused, but it still finishes the seek of the audio only file
+                        if !play_ctrl.is_paused_seek()
+                            && let Err(e) = play_ctrl.play_audio(frame, &mut play_ctrl.audio_producer.lock())
+                        {
                             log::error!("{e}");
                         }
+                        if play_ctrl.video_stream_time_base.is_none() {
+                            play_ctrl.finish_seek(serial);
+                        }
                         empty_count = 0;
                         continue;
                     }
@@ -527,14 +875,29 @@ impl Player {
         mut video_decoder: ffmpeg::decoder::Video,
         video_packet_receiver: kanal::Receiver<Option<ffmpeg::Packet>>,
         video_play_sender: kanal::Sender<VideoPlayFrame>,
-        mut graph: Option<ffmpeg::filter::Graph>,
+        sub_title_file: String,
+        time_base: ffmpeg::Rational,
     ) {
         let play_ctrl = self.play_ctrl.clone();
+        let video_filter_requests = self.video_filter_requests.clone();
+        let filter_error = self.filter_error.clone();
         let width = video_decoder.width() as usize;
         let height = video_decoder.height() as usize;
 
         let _ = std::thread::Builder::new().name("video decode".to_string()).spawn(move || {
+            // the frames in the channel, the playing one, and the one which is uploading
+            let mut converter = FrameConverter::new(VIDEO_FRAME_QUEUE_SIZE + 3);
+            let mut serial = play_ctrl.flushed_serial();
+            let mut filters = DecodeFilters::default();
+            let mut graph = Self::filter_graph(&video_decoder, &filters, &sub_title_file, time_base, &filter_error);
             'RUN: loop {
+                // only the newest filters are useful
+                let new_filters = video_filter_requests.lock().drain(..).next_back();
+                if let Some(new_filters) = new_filters {
+                    filters = new_filters;
+                    *filter_error.lock() = None;
+                    graph = Self::filter_graph(&video_decoder, &filters, &sub_title_file, time_base, &filter_error);
+                }
                 match play_ctrl.player_state.get() {
                     PlayerState::Stopped => {
                         log::info!("video decode exit");
@@ -542,10 +905,11 @@ impl Player {
                     }
                     PlayerState::EndOfFile => {}
                     PlayerState::Seeking(_) => {}
-                    PlayerState::Paused => {
+                    PlayerState::Paused if !play_ctrl.preroll() => {
                         spin_sleep::sleep(PLAY_MIN_INTERVAL);
                         continue 'RUN;
                     }
+                    PlayerState::Paused => {}
                     PlayerState::Playing => {}
                     PlayerState::Restarting => {}
                 }
@@ -566,7 +930,12 @@ impl Player {
                         continue;
                     }
                     Ok(Some(None)) => {
-                        //receive all frame
+                        //after seek, receive all frame
+                        serial = play_ctrl.flushed_serial();
+                        // drop the frames buffered in the filters, the others are kept, eg: "subtitles" parses the whole file when it is built
+                        if kits::FfmpegKit::has_stateful_filter(&filters.spec) {
+                            graph = Self::filter_graph(&video_decoder, &filters, &sub_title_file, time_base, &filter_error);
+                        }
                         let mut temp = ffmpeg::frame::Video::empty();
                         for _ in 0..20 {
                             if video_decoder.receive_frame(&mut temp).is_err() {
@@ -585,10 +954,11 @@ impl Player {
                         }
                         PlayerState::EndOfFile => {}
                         PlayerState::Seeking(_) => {}
-                        PlayerState::Paused => {
+                        PlayerState::Paused if !play_ctrl.preroll() => {
                             spin_sleep::sleep(PLAY_MIN_INTERVAL);
                             continue 'RUN;
                         }
+                        PlayerState::Paused => {}
                         PlayerState::Playing => {}
                         PlayerState::Restarting => {}
                     }
@@ -597,63 +967,65 @@ impl Player {
                         log::debug!("{e}");
                         continue 'RUN;
                     } else {
-                        let mut err_count = 0;
-                        let frame = {
-                            match graph {
-                                None => v_frame,
-                                Some(ref mut graph) => loop {
+                        let frames = match graph {
+                            None => vec![v_frame],
+                            Some(ref mut graph) => {
+                                if let Err(e) = graph.get("in").expect("").source().add(&v_frame) {
+                                    log::error!("{e}");
+                                    continue 'RUN;
+                                }
+                                // the deinterlace filters may need more frames, or output more frames
+                                let mut frames = Vec::new();
+                                loop {
                                     let mut filter_frame = ffmpeg::frame::Video::empty();
-                                    if let Err(e) = graph.get("in").expect("").source().add(&v_frame) {
-                                        log::error!("{e}");
-                                        continue 'RUN;
-                                    }
-
-                                    if let Err(e) = graph.get("out").expect("").sink().frame(&mut filter_frame) {
-                                        log::error!("{e}");
-                                        err_count += 1;
-                                    } else {
-                                        break filter_frame;
+                                    if graph.get("out").expect("").sink().frame(&mut filter_frame).is_err() {
+                                        break;
                                     }
-                                    if err_count > 3 {
-                                        continue 'RUN;
-                                    }
-                                },
-                            }
-                        };
-                        let color_image = match Self::frame_to_color_image(&frame) {
-                            Err(e) => {
-                                log::error!("{e}");
-                                continue;
+                                    frames.push(filter_frame);
+                                }
+                                frames
                             }
-                            Ok(t) => t,
                         };
+                        for frame in frames {
+                            let (scaled_width, scaled_height) = FrameConverter::scaled_size(&frame, play_ctrl.video_scale.get());
+                            let color_image = match converter.convert(&frame, scaled_width, scaled_height) {
+                                Err(e) => {
+                                    log::error!("{e}");
+                                    continue;
+                                }
+                                Ok(t) => t,
+                            };
 
-                        let (duration, pts) = {
-                            let packet_frame = frame.packet();
-                            let pts = frame.pts().unwrap_or_else(|| {
-                                log::debug!("Frame pts is none");
-                                frame.timestamp().unwrap_or_else(|| unsafe {
-                                    match (*frame.as_ptr()).pkt_dts {
-                                        ffmpeg::ffi::AV_NOPTS_VALUE => 0,
-                                        t => t,
-                                    }
-                                })
-                            });
-                            (packet_frame.duration, pts)
-                        };
+                            let (duration, pts) = {
+                                let packet_frame = frame.packet();
+                                let pts = frame.pts().unwrap_or_else(|| {
+                                    log::debug!("Frame pts is none");
+                                    frame.timestamp().unwrap_or_else(|| unsafe {
+                                        match (*frame.as_ptr()).pkt_dts {
+                                            ffmpeg::ffi::AV_NOPTS_VALUE => 0,
+                                            t => t,
+                                        }
+                                    })
+                                });
+                                (packet_frame.duration, pts)
+                            };
 
-                        let video_frame = VideoPlayFrame {
-                            width,
-                            height,
-                            pts,
-                            duration,
-                            timestamp: frame.timestamp().unwrap_or_default(),
-                            color_image,
-                        };
-                        if let Err(e) = video_play_sender.send(video_frame) {
-                            log::error!("{e}");
+                            let video_frame = VideoPlayFrame {
+                                width,
+                                height,
+                                pts,
+                                duration,
+                                timestamp: frame.timestamp().unwrap_or_default(),
+                                serial,
+                                crop: Self::crop_detected(&frame),
+                                alpha: kits::FfmpegKit::has_alpha(frame.format()),
+                                color_image,
+                            };
+                            if let Err(e) = video_play_sender.send(video_frame) {
+                                log::error!("{e}");
+                            }
+                            // spin_sleep::sleep(std::time::Duration::from_millis(2));
                         }
-                        // spin_sleep::sleep(std::time::Duration::from_millis(2));
                     }
                 }
             }
@@ -684,8 +1056,7 @@ impl Player {
                     PlayerState::Restarting => {}
                 }
 
-                if let CommandGo::Frame(t) = play_ctrl.command_go.get() {
-                    play_ctrl.command_go.set(CommandGo::None);
+                if let Some(Command { id, go: CommandGo::Frame(t) }) = play_ctrl.commands.pop_if(|go| matches!(go, CommandGo::Frame(_))) {
                     for _ in 1..t {
                         loop {
                             if let Ok(Some(_)) = video_play_receiver.try_recv() {
@@ -693,6 +1064,7 @@ impl Player {
                             }
                         }
                     }
+                    play_ctrl.commands.complete(id);
                 }
 
                 match video_play_receiver.try_recv() {
@@ -709,16 +1081,24 @@ impl Player {
                         if play_ctrl.packet_finished() {
                             empty_count += 1;
                             if empty_count == 10 {
+                                // keep the thread, it can play again after seek
                                 play_ctrl.set_video_finished(true);
-                                log::info!("video play exit");
-                                break;
+                                log::info!("video play finished");
                             }
                         }
+                        if play_ctrl.video_finished() {
+                            spin_sleep::sleep(PLAY_MIN_INTERVAL);
+                        }
                     }
                     Ok(Some(frame)) => {
+                        if play_ctrl.is_stale(frame.serial) {
+                            continue;
+                        }
+                        let serial = frame.serial;
                         if let Err(e) = play_ctrl.play_video(frame, &ctx) {
                             log::error!("{e}");
                         }
+                        play_ctrl.finish_seek(serial);
                         empty_count = 0;
                         continue;
                     }
@@ -818,19 +1198,25 @@ impl Player {
                     break;
                 }
 
-                if (audio_packet_sender.is_none() || play_ctrl.audio_finished()) && (video_packet_sender.is_none() || play_ctrl.video_finished()) {
-                    play_ctrl.player_state.set(PlayerState::Stopped);
-                    log::info!("read packet exit");
-                    break;
+                if (audio_packet_sender.is_none() || play_ctrl.audio_finished())
+                    && (video_packet_sender.is_none() || play_ctrl.video_finished())
+                    && !matches!(play_ctrl.player_state.get(), PlayerState::EndOfFile | PlayerState::Seeking(_))
+                {
+                    // keep the last frame and position, it can be seeked and played again
+                    play_ctrl.player_state.set(PlayerState::EndOfFile);
+                    log::info!("end of file");
                 }
 
                 let mut packets = 1;
-                match play_ctrl.command_go.get() {
+                // the frame commands are done by the video play thread
+                let command = play_ctrl.commands.pop_if(|go| !matches!(go, CommandGo::Frame(_)));
+                let command_id = command.map(|c| c.id).unwrap_or_default();
+                match command.map(|c| c.go).unwrap_or(CommandGo::None) {
                     CommandGo::Packet(next_amount) => {
-                        play_ctrl.command_go.set(CommandGo::None);
                         for _ in 1..next_amount {
                             if input.packets().next().is_none() {
                                 play_ctrl.set_packet_finished(true);
+                                play_ctrl.commands.complete(command_id);
                                 spin_sleep::sleep(PLAY_MIN_INTERVAL);
                                 continue 'PACKETS;
                             }
@@ -838,11 +1224,14 @@ impl Player {
                     }
                     CommandGo::GoMs(ms) => {
                         log::info!("go ms: {ms}");
-                        play_ctrl.command_go.set(CommandGo::None);
                         let mut diff = play_ctrl.elapsed_ms() + ms;
                         if diff < 0 {
                             diff = 0;
                         }
+                        if let PlayerState::Seeking(_) = play_ctrl.player_state.get() {
+                            // show the target on the seekbar
+                            play_ctrl.video_elapsed_ms_override.set(diff.min(play_ctrl.duration_ms));
+                        }
                         let seek_pos = (diff * duration) / play_ctrl.duration_ms;
                         {
                             let re = if ms > 0 {
@@ -856,18 +1245,19 @@ impl Player {
                         }
 
                         video_audio_rs.seek_clean();
+                        play_ctrl.set_flushed_serial(command_id);
                         if let Some(a) = &audio_packet_sender {
                             let _ = a.send(None);
                         }
                         if let Some(v) = &video_packet_sender {
                             let _ = v.send(None);
                         }
-                        if let PlayerState::Paused = play_ctrl.player_state.get() {
+                        play_ctrl.reset_finished();
+                        if play_ctrl.is_paused() || play_ctrl.is_paused_seek() {
                             packets = 2;
                         }
                     }
                     CommandGo::Seek(t) => {
-                        play_ctrl.command_go.set(CommandGo::None);
                         let seek_pos = {
                             if t > play_ctrl.duration {
                                 play_ctrl.duration
@@ -881,20 +1271,23 @@ impl Player {
                             log::error!("{e}");
                         }
                         video_audio_rs.seek_clean();
+                        play_ctrl.set_flushed_serial(command_id);
                         if let Some(a) = &audio_packet_sender {
                             let _ = a.send(None);
                         }
                         if let Some(v) = &video_packet_sender {
                             let _ = v.send(None);
                         }
+                        play_ctrl.reset_finished();
 
                         //不是每一packet的数据都会有界面输出，所以会出现seek后且是pause时，画面没有到位，所以多输出一packet
-                        if let PlayerState::Paused = play_ctrl.player_state.get() {
+                        if play_ctrl.is_paused() || play_ctrl.is_paused_seek() {
                             packets = 2;
                         }
                     }
                     _ => {
-                        if play_ctrl.player_state.get() == PlayerState::Paused
+                        if (play_ctrl.is_paused() && !play_ctrl.preroll())
+                            || play_ctrl.player_state.get() == PlayerState::EndOfFile
                             || (audio_packet_sender.is_some() && audio_packet_sender.as_ref().expect("").is_full())
                             || (video_packet_sender.is_some() && video_packet_sender.as_ref().expect("").is_full())
                         {
@@ -904,6 +1297,10 @@ impl Player {
                     }
                 }
 
+                if command.is_some() {
+                    play_ctrl.commands.complete(command_id);
+                }
+
                 for _ in 0..packets {
                     if let Some((_, packet)) = input.packets().next() {
                         if unsafe { !packet.is_empty() } {
@@ -949,14 +1346,248 @@ impl Player {
 }
 
 impl Player {
-    pub fn ui(&mut self, ui: &mut Ui, size: [f32; 2]) -> egui::Response {
-        let image = egui::Image::new(SizedTexture::new(self.play_ctrl.texture_handle.id(), size)).sense(egui::Sense::click());
+    /// show the picture in the size, the uv is the rect of the texture to show
+    pub fn ui(&mut self, ui: &mut Ui, size: [f32; 2], uv: egui::Rect) -> egui::Response {
+        // set here, the texture is shared with the pre player while it is pre-rolled
+        if let Some(cover) = self.cover.take() {
+            self.play_ctrl.texture_handle.set(cover, egui::TextureOptions::LINEAR);
+        }
+        let image = egui::Image::new(SizedTexture::new(self.play_ctrl.texture_handle.id(), size))
+            .uv(self.zoom_uv(uv))
+            .sense(egui::Sense::click());
+        let background = ui.painter().add(egui::Shape::Noop);
         let response = ui.add(image);
+        if self.video_alpha.get() {
+            let checkerboard = self.checkerboard.get_or_insert_with(|| {
+                ui.ctx()
+                    .load_texture("checkerboard", AlphaBackground::checkerboard_image(), egui::TextureOptions::NEAREST_REPEAT)
+            });
+            ui.painter().set(background, self.alpha_background.shape(response.rect, checkerboard.id()));
+        }
+        self.zoom_pan(ui, &response, uv);
+        self.update_video_scale(ui, size, uv);
+        self.render_notice(ui, response.rect);
         self.render_status(ui, &response);
         self.process_state();
         response
     }
 
+    /// save the playing frame at the source resolution, and(or) copy it to the clipboard. it is done in a thread.
+    /// the frame is decoded again with the filters, the burned subtitles are excluded if `subtitles` is false
+    pub fn screenshot(&self, ctx: &egui::Context, format: Option<ScreenshotFormat>, copy: bool, subtitles: bool) {
+        let pts = self.playing_pts.get();
+        if pts == ffmpeg::ffi::AV_NOPTS_VALUE {
+            self.set_notice("no frame for the screenshot".to_string());
+            return;
+        }
+        let file = self.file.clone();
+        let sub_spec = if subtitles {
+            Self::subtitles_spec(&self.sub_title_file)
+        } else {
+            String::new()
+        };
+        let spec = self.filter_spec(self.filter_error().is_none(), String::new(), sub_spec);
+        let save_file = format.map(|f| (Screenshot::file_name(&self.file, self.elapsed_ms(), f), f));
+        let notice = self.notice.clone();
+        let ctx = ctx.clone();
+        let _ = std::thread::Builder::new().name("screenshot".to_string()).spawn(move || {
+            let re = (|| -> Result<String, anyhow::Error> {
+                let frame = Screenshot::decode_frame(&file, pts, &spec)?;
+                let image = Screenshot::image(&frame)?;
+                let mut messages = Vec::new();
+                if let Some((path, format)) = save_file {
+                    Screenshot::save(&image, &path, format)?;
+                    messages.push(format!("saved {}", path.display()));
+                }
+                if copy {
+                    ctx.copy_image(image);
+                    messages.push("copied to the clipboard".to_string());
+                }
+                Ok(format!("screenshot {}", messages.join(", ")))
+            })();
+            let message = match re {
+                Err(e) => {
+                    log::error!("screenshot: {e}");
+                    format!("screenshot failed: {e}")
+                }
+                Ok(m) => {
+                    log::info!("{m}");
+                    m
+                }
+            };
+            *notice.lock() = Some((message, Utc::now().timestamp_millis()));
+            ctx.request_repaint();
+        });
+    }
+
+    /// mark the in point of the clip at the playing position
+    pub fn mark_in(&mut self) {
+        let ms = self.elapsed_ms();
+        self.clip_in_ms = Some(ms);
+        if self.clip_out_ms.is_some_and(|out| out <= ms) {
+            self.clip_out_ms = None;
+        }
+        self.set_notice(format!("in: {}", Self::format_duration(chrono::Duration::milliseconds(ms))));
+    }
+
+    /// mark the out point of the clip at the playing position
+    pub fn mark_out(&mut self) {
+        let ms = self.elapsed_ms();
+        self.clip_out_ms = Some(ms);
+        if self.clip_in_ms.is_some_and(|t| t >= ms) {
+            self.clip_in_ms = None;
+        }
+        self.set_notice(format!("out: {}", Self::format_duration(chrono::Duration::milliseconds(ms))));
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.clip_in_ms = None;
+        self.clip_out_ms = None;
+    }
+
+    /// the range of the clip, none if there is no mark. the in point is the start of the file if it is not marked,
+    /// and the out point is the end
+    pub fn clip_range(&self) -> Option<(i64, i64)> {
+        if self.clip_in_ms.is_none() && self.clip_out_ms.is_none() {
+            return None;
+        }
+        let start = self.clip_in_ms.unwrap_or(0);
+        let end = self.clip_out_ms.unwrap_or(self.duration_ms);
+        (end > start).then_some((start, end))
+    }
+
+    /// export the range of the clip by copying the packets, in a thread
+    pub fn export_clip(&self) -> Option<ExportTask> {
+        let (start, end) = self.clip_range()?;
+        Some(ClipExport::spawn(&self.file, start, end))
+    }
+
+    /// export the range of the clip by encoding it with the preset, in a thread. the filters of the player are applied
+    pub fn export_encoded(&self, preset: ExportPreset) -> Option<ExportTask> {
+        let (start, end) = self.clip_range()?;
+        Some(EncodeExport::spawn(&self.file, start, end, &self.export_filter_spec(), preset))
+    }
+
+    /// export the sampled frames of the clip range, or the whole file if it is not marked, in a thread.
+    /// the filters of the player are applied
+    pub fn export_frames(&self, sampling: FrameSampling, output: FramesOutput) -> ExportTask {
+        let (start, end) = self.clip_range().unwrap_or((0, self.duration_ms));
+        FramesExport::spawn(&self.file, start, end, &self.export_filter_spec(), sampling, output)
+    }
+
+    fn set_notice(&self, message: String) {
+        *self.notice.lock() = Some((message, Utc::now().timestamp_millis()));
+    }
+
+    /// show the notice for a few seconds at the left top
+    fn render_notice(&self, ui: &mut Ui, rect: egui::Rect) {
+        let mut notice = self.notice.lock();
+        if let Some((message, ts)) = notice.as_ref() {
+            let diff = Utc::now().timestamp_millis() - ts;
+            if diff > NOTICE_MS {
+                *notice = None;
+                return;
+            }
+            ui.painter().text(
+                rect.left_top() + egui::vec2(10., 32.),
+                egui::Align2::LEFT_TOP,
+                message,
+                egui::FontId::proportional(16.),
+                egui::Color32::WHITE,
+            );
+            ui.ctx().request_repaint_after(std::time::Duration::from_millis((NOTICE_MS - diff) as u64));
+        }
+    }
+
+    /// the decode thread scales the picture down to the size on the screen.
+    /// the full resolution is used when it is zoomed or fullscreen
+    fn update_video_scale(&mut self, ui: &Ui, size: [f32; 2], uv: egui::Rect) {
+        let display = self.display_size();
+        let fullscreen = ui.ctx().input(|i| i.viewport().fullscreen.unwrap_or(false));
+        let scale = if self.zoom > 1. || fullscreen || display.min_elem() <= 0. || uv.width() <= 0. || uv.height() <= 0. {
+            1.
+        } else {
+            // the size of the whole picture in pixels, the uv may be a part of it
+            let picture = egui::Vec2::from(size) * ui.ctx().pixels_per_point() / uv.size();
+            let scale = (picture.x / display.x).max(picture.y / display.y);
+            // a step of 1/16, the scaler is not created again for the small changes of the window
+            ((scale * 16.).ceil() / 16.).clamp(1. / 16., 1.)
+        };
+        let old_scale = self.video_scale.get();
+        if scale != old_scale {
+            self.video_scale.set(scale);
+            // the paused picture is blurry, if it is bigger
+            if scale > old_scale {
+                self.refresh_frame();
+            }
+        }
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn reset_zoom(&mut self) {
+        self.zoom = 1.;
+        self.pan = egui::Vec2::ZERO;
+    }
+
+    /// the part of the uv to show, it is moved by the pan and scaled by the zoom
+    fn zoom_uv(&self, uv: egui::Rect) -> egui::Rect {
+        if self.zoom <= 1. {
+            return uv;
+        }
+        let size = uv.size() / self.zoom;
+        let max_pan = (uv.size() - size) / 2.;
+        let pan = self.pan.clamp(-max_pan, max_pan);
+        egui::Rect::from_center_size(uv.center() + pan, size)
+    }
+
+    /// keep the zoomed part in the uv
+    fn clamp_pan(&mut self, uv: egui::Rect) {
+        self.pan = self.zoom_uv(uv).center() - uv.center();
+    }
+
+    /// zoom by ctrl + wheel at the pointer, and pan by dragging when it is zoomed
+    fn zoom_pan(&mut self, ui: &mut Ui, image_res: &egui::Response, uv: egui::Rect) {
+        let rect = image_res.rect;
+        if rect.width() <= 0. || rect.height() <= 0. {
+            return;
+        }
+        let zoom_delta = ui.input(|i| i.zoom_delta());
+        if zoom_delta != 1.
+            && let Some(pointer) = ui.input(|i| i.pointer.hover_pos())
+            && rect.contains(pointer)
+        {
+            // keep the point under the pointer
+            let old_uv = self.zoom_uv(uv);
+            let frac = (pointer - rect.min) / rect.size();
+            let point = old_uv.min + frac * old_uv.size();
+            self.zoom = (self.zoom * zoom_delta).clamp(1., MAX_ZOOM);
+            let size = uv.size() / self.zoom;
+            let min = point - frac * size;
+            self.pan = (min + size / 2.) - uv.center();
+            self.clamp_pan(uv);
+        }
+
+        if self.zoom > 1. {
+            let pan_res = ui.interact(rect, image_res.id.with("zoom_pan"), egui::Sense::drag());
+            if pan_res.dragged() {
+                let size = self.zoom_uv(uv).size();
+                self.pan -= pan_res.drag_delta() / rect.size() * size;
+                self.clamp_pan(uv);
+            }
+            let text = format!("x{:.1}", self.zoom);
+            ui.painter().text(
+                rect.left_top() + egui::vec2(10., 10.),
+                egui::Align2::LEFT_TOP,
+                text,
+                egui::FontId::proportional(16.),
+                egui::Color32::WHITE,
+            );
+        }
+    }
+
     fn render_status(&mut self, ui: &mut Ui, image_res: &egui::Response) -> Option<egui::Rect> {
         {
             let temp_image_res = ui.interact(image_res.rect, image_res.id.with("image_sense"), egui::Sense::click());
@@ -972,9 +1603,12 @@ impl Player {
         let currently_seeking = matches!(self.player_state.get(), PlayerState::Seeking(_));
         let is_stopped = self.player_state.get() == PlayerState::Stopped;
         let is_paused = self.player_state.get() == PlayerState::Paused;
-        let seekbar_anim_frac =
-            ui.ctx()
-                .animate_bool_with_time(image_res.id.with("seekbar_anim"), hovered || currently_seeking || is_paused || is_stopped, 0.2);
+        let is_end = self.player_state.get() == PlayerState::EndOfFile;
+        let seekbar_anim_frac = ui.ctx().animate_bool_with_time(
+            image_res.id.with("seekbar_anim"),
+            hovered || currently_seeking || is_paused || is_stopped || is_end,
+            0.2,
+        );
 
         {
             if ui.input(|e| e.pointer.is_moving()) {
@@ -986,7 +1620,7 @@ impl Player {
             }
         }
 
-        if (is_paused || is_stopped || currently_seeking || self.show_seekbar()) && seekbar_anim_frac > 0. {
+        if (is_paused || is_stopped || is_end || currently_seeking || self.show_seekbar()) && seekbar_anim_frac > 0. {
             let seekbar_width_offset = 20.;
             let full_seek_bar_width = image_res.rect.width() - seekbar_width_offset;
 
@@ -1046,7 +1680,7 @@ impl Player {
             }
             let text_color = egui::Color32::WHITE.linear_multiply(seekbar_anim_frac);
 
-            let pause_icon = if is_paused {
+            let pause_icon = if is_paused || is_end {
                 "▶"
             } else if is_stopped {
                 "◼"
@@ -1101,6 +1735,59 @@ impl Player {
             ui.painter()
                 .rect_filled(full_seek_bar_rect, egui::CornerRadius::ZERO, full_seek_bar_color.linear_multiply(0.5));
             ui.painter().rect_filled(seekbar_rect, egui::CornerRadius::ZERO, seekbar_color);
+            let duration_ms = self.duration_ms;
+            if duration_ms > 0 {
+                if let Some((start, end)) = self.clip_range() {
+                    let x = |ms: i64| full_seek_bar_rect.left() + full_seek_bar_width * (ms as f32 / duration_ms as f32).clamp(0., 1.);
+                    let clip_color = egui::Color32::LIGHT_BLUE.linear_multiply(seekbar_anim_frac);
+                    let clip_rect = egui::Rect::from_x_y_ranges(x(start)..=x(end), full_seek_bar_rect.y_range());
+                    ui.painter().rect_filled(clip_rect, egui::CornerRadius::ZERO, clip_color.linear_multiply(0.5));
+                    for ms in [self.clip_in_ms, self.clip_out_ms].into_iter().flatten() {
+                        ui.painter().vline(
+                            x(ms),
+                            (full_seek_bar_rect.top() - 4.)..=(full_seek_bar_rect.bottom() + 4.),
+                            egui::Stroke::new(2., clip_color),
+                        );
+                    }
+                }
+                let tick_color = egui::Color32::YELLOW.linear_multiply(seekbar_anim_frac);
+                for chapter in self.chapters.iter().filter(|c| c.start_ms > 0 && c.start_ms < duration_ms) {
+                    let x = full_seek_bar_rect.left() + full_seek_bar_width * (chapter.start_ms as f32 / duration_ms as f32);
+                    ui.painter().vline(
+                        x,
+                        (full_seek_bar_rect.top() - 2.)..=(full_seek_bar_rect.bottom() + 2.),
+                        egui::Stroke::new(2., tick_color),
+                    );
+                }
+                if seekbar_hovered && let Some(hover_pos) = seekbar_response.hover_pos() {
+                    let hover_frac = ((hover_pos.x - full_seek_bar_rect.left()) / full_seek_bar_width).clamp(0., 1.);
+                    let hover_ms = (hover_frac as f64 * duration_ms as f64) as i64;
+                    let mut hover_text = Self::format_duration(chrono::Duration::milliseconds(hover_ms));
+                    if let Some(index) = Chapter::index_at(&self.chapters, hover_ms) {
+                        hover_text = format!("{hover_text}  {}", self.chapters[index].title);
+                    }
+                    let hover_text_rect = ui.painter().text(
+                        egui::pos2(hover_pos.x, full_seek_bar_rect.top() - 12.),
+                        egui::Align2::CENTER_BOTTOM,
+                        hover_text,
+                        egui::FontId::proportional(14.0),
+                        text_color,
+                    );
+                    if let Some(thumbnails) = &mut self.thumbnails
+                        && let Some(texture) = thumbnails.texture(ui.ctx(), hover_ms)
+                    {
+                        let size = texture.size_vec2();
+                        let x = (hover_pos.x - size.x / 2.).clamp(image_res.rect.left(), (image_res.rect.right() - size.x).max(image_res.rect.left()));
+                        let thumbnail_rect = egui::Rect::from_min_size(egui::pos2(x, hover_text_rect.top() - 4. - size.y), size);
+                        ui.painter().image(
+                            texture.id(),
+                            thumbnail_rect,
+                            egui::Rect::from_min_max(egui::pos2(0., 0.), egui::pos2(1., 1.)),
+                            egui::Color32::WHITE.linear_multiply(seekbar_anim_frac),
+                        );
+                    }
+                }
+            }
             let pause_icon_rect = ui
                 .painter()
                 .text(pause_icon_pos, egui::Align2::LEFT_BOTTOM, pause_icon, icon_font_id.clone(), text_color);
@@ -1196,13 +1883,69 @@ impl Player {
         self.set_state(PlayerState::Stopped);
     }
     pub fn seek(&mut self, frame_number: i64) {
-        self.command_go.set(CommandGo::Seek(frame_number));
+        self.go(CommandGo::Seek(frame_number));
+    }
+
+    /// push the command to the player threads, return the id of it
+    pub fn go(&mut self, go: CommandGo) -> u64 {
+        let id = self.commands.push(go);
+        match go {
+            CommandGo::GoMs(_) => self.begin_seek_ms(id, None),
+            CommandGo::Seek(t) if self.duration > 0 => {
+                self.begin_seek_ms(id, Some(t.clamp(0, self.duration) * self.duration_ms / self.duration));
+            }
+            // there is no video play thread to do it
+            CommandGo::Frame(_) if self.audio_only() => self.commands.complete(id),
+            _ => {}
+        }
+        id
+    }
+
+    /// show the target on the seekbar until the first frame after seeking is played.
+    /// if the target is none, it is computed by the read packet thread
+    fn begin_seek_ms(&mut self, id: u64, target_ms: Option<i64>) {
+        self.begin_seek(id);
+        self.last_seek_ms = target_ms.map(|ms| ms.clamp(0, self.duration_ms));
+        self.seek_start_ts = Utc::now().timestamp_millis();
+    }
+
+    pub fn seek_ms(&mut self, ms: i64) {
+        if self.duration_ms > 0 {
+            let seek_pos = (ms * self.duration) / self.duration_ms;
+            self.seek(seek_pos);
+        }
     }
 
     pub fn tab_seek(&mut self) {
         if self.tab_seek_ms > 0 {
-            let seek_pos = (self.tab_seek_ms * self.duration) / self.duration_ms;
-            self.seek(seek_pos);
+            self.seek_ms(self.tab_seek_ms);
+        }
+    }
+
+    /// seek to the start of next chapter, return false if there is no next chapter
+    pub fn next_chapter(&mut self) -> bool {
+        let elapsed_ms = self.elapsed_ms();
+        match self.chapters.iter().find(|c| c.start_ms > elapsed_ms) {
+            Some(c) => {
+                let start_ms = c.start_ms;
+                self.seek_ms(start_ms);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// seek to the start of current chapter, or previous chapter if it is at the start of current chapter.
+    pub fn pre_chapter(&mut self) -> bool {
+        // in the first second of a chapter, go to the previous one
+        let elapsed_ms = self.elapsed_ms() - 1000;
+        match Chapter::index_at(&self.chapters, elapsed_ms) {
+            Some(i) => {
+                let start_ms = self.chapters[i].start_ms;
+                self.seek_ms(start_ms);
+                true
+            }
+            None => false,
         }
     }
     // seek in play ctrl
@@ -1221,6 +1964,10 @@ impl Player {
             PlayerState::Playing => {
                 self.pause();
             }
+            PlayerState::EndOfFile => {
+                // play from the start
+                self.reset();
+            }
             _ => {}
         }
     }
@@ -1236,16 +1983,16 @@ impl Player {
     //     self.next_packet_frame.set(PacketFrame::Frame);
     // }
 
-    pub fn go_ahead_ui(&mut self, command_go_ui: &Shared<CommandGo>) {
-        self.command_go.set(command_go_ui.get());
+    /// return the id of the command, see [crate::player::CommandQueue::is_completed]
+    pub fn go_ahead_ui(&mut self, command_go_ui: &Shared<CommandGo>) -> u64 {
+        self.go(command_go_ui.get())
     }
-    pub fn go_back_ui(&mut self, command_go_ui: &Shared<CommandGo>) {
+    pub fn go_back_ui(&mut self, command_go_ui: &Shared<CommandGo>) -> u64 {
         match command_go_ui.get() {
-            CommandGo::Frame(t) => self.command_go.set(CommandGo::Frame(-t)),
-            CommandGo::Packet(t) => self.command_go.set(CommandGo::Packet(-t)),
-            CommandGo::GoMs(t) => self.command_go.set(CommandGo::GoMs(-t)),
-            CommandGo::None => self.command_go.set(CommandGo::None),
-            _ => {}
+            CommandGo::Frame(t) => self.go(CommandGo::Frame(-t)),
+            CommandGo::Packet(t) => self.go(CommandGo::Packet(-t)),
+            CommandGo::GoMs(t) => self.go(CommandGo::GoMs(-t)),
+            _ => self.go(CommandGo::None),
         }
     }
     pub fn get_mute(&self) -> bool {
@@ -1270,6 +2017,7 @@ impl Player {
                 // self.audio_dev.set_pause(true);
             }
             PlayerState::Playing => {
+                self.set_preroll(false);
                 self.audio_dev.resume();
             }
             PlayerState::Restarting => {
@@ -1295,7 +2043,7 @@ impl Player {
             Self::format_duration(chrono::Duration::milliseconds(self.duration_ms))
         )
     }
-    fn format_duration(dur: chrono::Duration) -> String {
+    pub fn format_duration(dur: chrono::Duration) -> String {
         let dt = DateTime::<Utc>::from(UNIX_EPOCH) + dur;
         if dt.format("%H").to_string().parse::<i64>().unwrap() > 0 {
             dt.format("%H:%M:%S").to_string()
@@ -1309,27 +2057,22 @@ impl Player {
 
         match self.player_state.get() {
             PlayerState::EndOfFile => {
-                self.player_state.set(PlayerState::Stopped);
+                if self.repeat_current {
+                    self.reset();
+                }
             }
             PlayerState::Stopped => {
                 //todo
             }
             PlayerState::Seeking(seek_in_progress) => {
-                if self.last_seek_ms.is_some() {
-                    // let video_elapsed_ms = self.video_elapsed_ms.get();
-                    let last_seek_ms = *self.last_seek_ms.as_ref().unwrap();
-                    // if (millisecond_approx_eq(video_elapsed_ms, last_seek_ms) || video_elapsed_ms == 0)
-                    if !seek_in_progress {
-                        // if let Some(previous_player_state) = self.pre_seek_player_state {
-                        //     self.set_state(previous_player_state)
-                        // }
-                        self.video_elapsed_ms_override.set(-1);
-                        self.last_seek_ms = None;
-                    } else {
-                        self.video_elapsed_ms_override.set(last_seek_ms);
-                    }
-                } else {
+                // no frame after seeking, eg: seek to the end. the seek may be still queued or running on the read packet thread
+                let timeout = self.commands.is_completed(self.seek_serial()) && Utc::now().timestamp_millis() - self.seek_start_ts > SEEK_TIMEOUT_MS;
+                if !seek_in_progress || timeout {
                     self.video_elapsed_ms_override.set(-1);
+                    self.last_seek_ms = None;
+                    self.set_state(self.pre_seek_state.get());
+                } else if let Some(last_seek_ms) = self.last_seek_ms {
+                    self.video_elapsed_ms_override.set(last_seek_ms);
                 }
             }
             PlayerState::Restarting => reset_stream = true,
@@ -1356,6 +2099,15 @@ impl Drop for Player {
     }
 }
 
+/// the filters of the video decode thread, they are before the subtitles
+#[derive(Default, Clone, Debug)]
+struct DecodeFilters {
+    /// all filters
+    spec: String,
+    /// the filters without the user filters: tone map, transform, crop and color. they are used if the user filters are invalid
+    builtin_spec: String,
+}
+
 fn to_sample(sample_format: cpal::SampleFormat) -> ffmpeg::format::Sample {
     use cpal::SampleFormat;
     use ffmpeg::format::{Sample, sample::Type as SampleType};
@@ -0,0 +1,478 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum RepeatMode {
+    /// stop at the end of the playlist
+    #[default]
+    Off,
+    /// repeat the current entry
+    One,
+    /// go to the first entry at the end of the playlist
+    All,
+}
+
+impl RepeatMode {
+    pub const ALL: [RepeatMode; 3] = [RepeatMode::Off, RepeatMode::One, RepeatMode::All];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RepeatMode::Off => "Repeat Off",
+            RepeatMode::One => "Repeat One",
+            RepeatMode::All => "Repeat All",
+        }
+    }
+}
+
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct PlaylistEntry {
+    pub path: String,
+    pub title: Option<String>,
+    /// seconds, none if it is unknown
+    pub duration: Option<i64>,
+}
+
+impl PlaylistEntry {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn display_name(&self) -> String {
+        match &self.title {
+            Some(t) => t.clone(),
+            None => Path::new(&self.path)
+                .file_name()
+                .map(|f| f.to_string_lossy().to_string())
+                .unwrap_or_else(|| self.path.clone()),
+        }
+    }
+}
+
+/// ordered entries to play, support m3u, m3u8 and pls
+#[derive(Default, Clone, Debug)]
+pub struct Playlist {
+    entries: Vec<PlaylistEntry>,
+    current: Option<usize>,
+    shuffle: bool,
+    pub repeat: RepeatMode,
+    /// the play order, it is the index of entries
+    order: Vec<usize>,
+    seed: u64,
+}
+
+impl Playlist {
+    pub const EXTENSIONS: [&'static str; 3] = ["m3u", "m3u8", "pls"];
+
+    pub fn is_playlist_file(file: &Path) -> bool {
+        file.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| Self::EXTENSIONS.contains(&e.to_lowercase().as_str()))
+    }
+
+    pub fn entries(&self) -> &[PlaylistEntry] {
+        &self.entries
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+    pub fn current_entry(&self) -> Option<&PlaylistEntry> {
+        self.current.and_then(|i| self.entries.get(i))
+    }
+
+    pub fn shuffle(&self) -> bool {
+        self.shuffle
+    }
+    pub fn set_shuffle(&mut self, shuffle: bool) {
+        self.shuffle = shuffle;
+        self.reorder();
+    }
+
+    /// if shuffle, the entry is put at a random place of the entries which are not played
+    pub fn add(&mut self, entry: PlaylistEntry) {
+        self.entries.push(entry);
+        let index = self.entries.len() - 1;
+        if !self.shuffle {
+            self.order.push(index);
+            return;
+        }
+        let start = self.current_pos().map_or(0, |p| p + 1);
+        let pos = start + self.random(self.order.len() - start + 1);
+        self.order.insert(pos, index);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<PlaylistEntry> {
+        if index >= self.entries.len() {
+            return None;
+        }
+        let entry = self.entries.remove(index);
+        self.current = match self.current {
+            Some(c) if c == index => None,
+            Some(c) if c > index => Some(c - 1),
+            c => c,
+        };
+        self.order.retain(|i| *i != index);
+        for i in &mut self.order {
+            if *i > index {
+                *i -= 1;
+            }
+        }
+        Some(entry)
+    }
+
+    /// move the entry from index `from` to index `to`
+    pub fn move_entry(&mut self, from: usize, to: usize) {
+        if from >= self.entries.len() || to >= self.entries.len() || from == to {
+            return;
+        }
+        let entry = self.entries.remove(from);
+        self.entries.insert(to, entry);
+        let moved = |i: usize| {
+            if i == from {
+                to
+            } else if from < i && i <= to {
+                i - 1
+            } else if to <= i && i < from {
+                i + 1
+            } else {
+                i
+            }
+        };
+        self.current = self.current.map(moved);
+        // the shuffled order is kept, the order of entries is changed
+        if self.shuffle {
+            self.order.iter_mut().for_each(|i| *i = moved(*i));
+        } else {
+            self.reorder();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.current = None;
+    }
+
+    pub fn set_current(&mut self, index: usize) -> Option<&PlaylistEntry> {
+        if index < self.entries.len() {
+            self.current = Some(index);
+        }
+        self.current_entry()
+    }
+
+    /// select the entry of the path, return false if it is not in the playlist
+    pub fn select_path(&mut self, path: &str) -> bool {
+        match self.entries.iter().position(|e| e.path == path) {
+            Some(i) => {
+                self.current = Some(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// the next entry to play. if `auto` is true, the current one is finished, follow the repeat mode
+    pub fn next(&mut self, auto: bool) -> Option<&PlaylistEntry> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        if auto && self.repeat == RepeatMode::One && self.current.is_some() {
+            return self.current_entry();
+        }
+        let next = match self.current_pos() {
+            None => 0,
+            Some(p) if p + 1 < self.order.len() => p + 1,
+            Some(_) => {
+                if auto && self.repeat == RepeatMode::Off {
+                    return None;
+                }
+                if self.shuffle {
+                    // a new shuffled pass, the finished one is the first, so it is not played again
+                    self.reorder();
+                    if self.order.len() > 1 { 1 } else { 0 }
+                } else {
+                    0
+                }
+            }
+        };
+        self.current = Some(self.order[next]);
+        self.current_entry()
+    }
+
+    /// the entry which [Playlist::next] goes to, but the playlist is not changed.
+    /// none at the end of the shuffled order, because it is shuffled again by [Playlist::next]
+    pub fn peek_next(&self, auto: bool) -> Option<&PlaylistEntry> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        if auto && self.repeat == RepeatMode::One && self.current.is_some() {
+            return self.current_entry();
+        }
+        let next = match self.current_pos() {
+            None => 0,
+            Some(p) if p + 1 < self.order.len() => p + 1,
+            Some(_) if (auto && self.repeat == RepeatMode::Off) || self.shuffle => return None,
+            Some(_) => 0,
+        };
+        self.entries.get(self.order[next])
+    }
+
+    /// the previous entry to play
+    pub fn pre(&mut self) -> Option<&PlaylistEntry> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let pre = match self.current_pos() {
+            Some(p) if p > 0 => p - 1,
+            _ => self.order.len() - 1,
+        };
+        self.current = Some(self.order[pre]);
+        self.current_entry()
+    }
+
+    /// the position of current entry in the play order
+    fn current_pos(&self) -> Option<usize> {
+        self.current.and_then(|c| self.order.iter().position(|i| *i == c))
+    }
+
+    /// a random number in 0..n by xorshift, n must be bigger than 0
+    fn random(&mut self, n: usize) -> usize {
+        if self.seed == 0 {
+            self.seed = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(1) as u64 | 1;
+        }
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        (self.seed % n as u64) as usize
+    }
+
+    /// compute the play order, if shuffle the current entry is the first one
+    fn reorder(&mut self) {
+        self.order = (0..self.entries.len()).collect();
+        if !self.shuffle {
+            return;
+        }
+        // Fisher–Yates
+        for i in (1..self.order.len()).rev() {
+            let j = self.random(i + 1);
+            self.order.swap(i, j);
+        }
+        if let Some(c) = self.current
+            && let Some(p) = self.order.iter().position(|i| *i == c)
+        {
+            self.order.swap(0, p);
+        }
+    }
+}
+
+impl Playlist {
+    /// load m3u, m3u8 or pls file
+    pub fn load(file: &Path) -> Result<Self, anyhow::Error> {
+        let bytes = fs::read(file)?;
+        let text = String::from_utf8_lossy(&bytes);
+        let base = file.parent().unwrap_or(Path::new(""));
+        let is_pls = file.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("pls"));
+        let mut playlist = Self::default();
+        let entries = if is_pls { Self::parse_pls(&text, base) } else { Self::parse_m3u(&text, base) };
+        for entry in entries {
+            playlist.add(entry);
+        }
+        Ok(playlist)
+    }
+
+    /// save to m3u, m3u8 or pls file, by the extension of the file
+    pub fn save(&self, file: &Path) -> Result<(), anyhow::Error> {
+        let is_pls = file.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("pls"));
+        let text = if is_pls { self.to_pls() } else { self.to_m3u() };
+        fs::write(file, text)?;
+        Ok(())
+    }
+
+    pub fn parse_m3u(text: &str, base: &Path) -> Vec<PlaylistEntry> {
+        let mut entries = Vec::new();
+        let mut title = None;
+        let mut duration = None;
+        for line in text.lines() {
+            let line = line.trim().trim_start_matches('\u{feff}');
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(info) = line.strip_prefix("#EXTINF:") {
+                let (d, t) = info.split_once(',').unwrap_or((info, ""));
+                duration = d.trim().parse::<i64>().ok().filter(|d| *d >= 0);
+                title = Some(t.trim().to_string()).filter(|t| !t.is_empty());
+                continue;
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+            entries.push(PlaylistEntry {
+                path: Self::resolve(line, base),
+                title: title.take(),
+                duration: duration.take(),
+            });
+        }
+        entries
+    }
+
+    pub fn parse_pls(text: &str, base: &Path) -> Vec<PlaylistEntry> {
+        let mut entries = BTreeMap::<u32, PlaylistEntry>::new();
+        for line in text.lines() {
+            let Some((key, value)) = line.trim().split_once('=') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+            let (name, number) = key.split_at(key.find(|c: char| c.is_ascii_digit()).unwrap_or(key.len()));
+            let Ok(number) = number.parse::<u32>() else {
+                continue;
+            };
+            let entry = entries.entry(number).or_default();
+            match name {
+                "file" => entry.path = Self::resolve(value, base),
+                "title" => entry.title = Some(value.to_string()).filter(|t| !t.is_empty()),
+                "length" => entry.duration = value.parse::<i64>().ok().filter(|d| *d >= 0),
+                _ => {}
+            }
+        }
+        entries.into_values().filter(|e| !e.path.is_empty()).collect()
+    }
+
+    pub fn to_m3u(&self) -> String {
+        let mut text = String::from("#EXTM3U\n");
+        for e in &self.entries {
+            if e.title.is_some() || e.duration.is_some() {
+                let _ = writeln!(text, "#EXTINF:{},{}", e.duration.unwrap_or(-1), e.title.as_deref().unwrap_or_default());
+            }
+            let _ = writeln!(text, "{}", e.path);
+        }
+        text
+    }
+
+    pub fn to_pls(&self) -> String {
+        let mut text = String::from("[playlist]\n");
+        for (i, e) in self.entries.iter().enumerate() {
+            let n = i + 1;
+            let _ = writeln!(text, "File{n}={}", e.path);
+            if let Some(t) = &e.title {
+                let _ = writeln!(text, "Title{n}={t}");
+            }
+            let _ = writeln!(text, "Length{n}={}", e.duration.unwrap_or(-1));
+        }
+        let _ = writeln!(text, "NumberOfEntries={}", self.entries.len());
+        let _ = writeln!(text, "Version=2");
+        text
+    }
+
+    /// relative path is relative to the dir of playlist, url is not changed
+    fn resolve(path: &str, base: &Path) -> String {
+        let path = path.strip_prefix("file://").unwrap_or(path);
+        if path.contains("://") {
+            return path.to_string();
+        }
+        let p = PathBuf::from(path);
+        if p.is_absolute() {
+            path.to_string()
+        } else {
+            base.join(p).to_string_lossy().to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{Playlist, PlaylistEntry, RepeatMode};
+
+    #[test]
+    fn test_parse() {
+        let m3u = "#EXTM3U\n#EXTINF:123,First\n/media/a.mkv\n\nhttp://host/b.mp4\nc.mp3\n";
+        let entries = Playlist::parse_m3u(m3u, Path::new("/music"));
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].title.as_deref(), Some("First"));
+        assert_eq!(entries[0].duration, Some(123));
+        assert_eq!(entries[1].path, "http://host/b.mp4");
+        assert_eq!(entries[2].path, Path::new("/music").join("c.mp3").to_string_lossy());
+
+        let pls = "[playlist]\nFile2=/b.mp4\nFile1=/a.mp4\nTitle1=A\nLength1=-1\nNumberOfEntries=2\nVersion=2\n";
+        let entries = Playlist::parse_pls(pls, Path::new("/"));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, "/a.mp4");
+        assert_eq!(entries[0].title.as_deref(), Some("A"));
+        assert_eq!(entries[0].duration, None);
+    }
+
+    #[test]
+    fn test_next() {
+        let mut playlist = Playlist::default();
+        for p in ["/a", "/b", "/c"] {
+            playlist.add(PlaylistEntry::new(p));
+        }
+        assert_eq!(playlist.next(true).map(|e| e.path.clone()), Some("/a".to_string()));
+        playlist.move_entry(0, 2);
+        assert_eq!(playlist.current(), Some(2));
+        assert!(playlist.next(true).is_none());
+        playlist.repeat = RepeatMode::All;
+        assert_eq!(playlist.next(true).map(|e| e.path.clone()), Some("/b".to_string()));
+        playlist.repeat = RepeatMode::One;
+        assert_eq!(playlist.next(true).map(|e| e.path.clone()), Some("/b".to_string()));
+        assert_eq!(playlist.pre().map(|e| e.path.clone()), Some("/a".to_string()));
+    }
+
+    #[test]
+    fn test_peek_next() {
+        let mut playlist = Playlist::default();
+        for p in ["/a", "/b", "/c", "/d"] {
+            playlist.add(PlaylistEntry::new(p));
+        }
+        playlist.set_shuffle(true);
+        playlist.next(false);
+        let current = playlist.current();
+        let peeked = playlist.peek_next(true).map(|e| e.path.clone());
+        assert_eq!(playlist.current(), current);
+        assert_eq!(playlist.next(true).map(|e| e.path.clone()), peeked);
+    }
+
+    #[test]
+    fn test_shuffle_repeat_all() {
+        let mut playlist = Playlist::default();
+        for p in ["/a", "/b", "/c", "/d"] {
+            playlist.add(PlaylistEntry::new(p));
+        }
+        playlist.set_shuffle(true);
+        playlist.repeat = RepeatMode::All;
+        let mut last = playlist.next(true).map(|e| e.path.clone());
+        for i in 0..40 {
+            let next = playlist.next(i % 3 != 0).map(|e| e.path.clone());
+            assert!(next.is_some());
+            assert_ne!(next, last);
+            last = next;
+        }
+
+        // the queued order is kept when the entries are changed
+        let queued: Vec<usize> = playlist.order.clone();
+        playlist.add(PlaylistEntry::new("/e"));
+        assert_eq!(playlist.order.iter().filter(|i| **i != 4).copied().collect::<Vec<_>>(), queued);
+        let removed = playlist.order[0];
+        playlist.remove(removed);
+        let expected: Vec<usize> = queued
+            .iter()
+            .filter(|i| **i != removed)
+            .map(|i| if *i > removed { i - 1 } else { *i })
+            .collect();
+        assert_eq!(playlist.order.iter().filter(|i| **i != 3).copied().collect::<Vec<_>>(), expected);
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ffmpeg::{Rescale, software::scaling};
+
+use crate::player::{
+    AV_TIME_BASE_RATIONAL, MILLISECOND_TIME_BASE, Player,
+    kits::{FfmpegKit, data_dir_file, ms_file_name},
+};
+
+/// the image format of the screenshot, it is encoded by ffmpeg
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum ScreenshotFormat {
+    #[default]
+    Png,
+    Jpeg,
+}
+
+impl ScreenshotFormat {
+    pub const ALL: [ScreenshotFormat; 2] = [ScreenshotFormat::Png, ScreenshotFormat::Jpeg];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "PNG",
+            ScreenshotFormat::Jpeg => "JPEG",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.name() == name)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "png",
+            ScreenshotFormat::Jpeg => "jpg",
+        }
+    }
+
+    /// encode the image by the png or mjpeg encoder. the png keeps the alpha channel
+    pub fn encode(&self, image: &egui::ColorImage) -> Result<Vec<u8>, anyhow::Error> {
+        let (width, height) = (image.width() as u32, image.height() as u32);
+        let alpha = image.pixels.iter().any(|p| p.a() != 255);
+        let (id, format) = match self {
+            ScreenshotFormat::Png if alpha => (ffmpeg::codec::Id::PNG, ffmpeg::format::Pixel::RGBA),
+            ScreenshotFormat::Png => (ffmpeg::codec::Id::PNG, ffmpeg::format::Pixel::RGB24),
+            ScreenshotFormat::Jpeg => (ffmpeg::codec::Id::MJPEG, ffmpeg::format::Pixel::YUVJ444P),
+        };
+
+        // the pixels of egui are premultiplied, the encoders want the straight alpha
+        let mut rgba = ffmpeg::frame::Video::new(ffmpeg::format::Pixel::RGBA, width, height);
+        let stride = rgba.stride(0);
+        let row_bytes = width as usize * 4;
+        for (y, row) in image.as_raw().chunks_exact(row_bytes).enumerate() {
+            let line = &mut rgba.data_mut(0)[y * stride..y * stride + row_bytes];
+            line.copy_from_slice(row);
+            if alpha {
+                for p in line.chunks_exact_mut(4) {
+                    p.copy_from_slice(&egui::Color32::from_rgba_premultiplied(p[0], p[1], p[2], p[3]).to_srgba_unmultiplied());
+                }
+            }
+        }
+        let mut picture = ffmpeg::frame::Video::empty();
+        scaling::Context::get(
+            rgba.format(),
+            width,
+            height,
+            format,
+            width,
+            height,
+            scaling::Flags::BILINEAR | scaling::Flags::ACCURATE_RND,
+        )?
+        .run(&rgba, &mut picture)?;
+
+        let codec = ffmpeg::encoder::find(id).ok_or(ffmpeg::Error::EncoderNotFound)?;
+        let mut encoder = ffmpeg::codec::context::Context::new_with_codec(codec).encoder().video()?;
+        encoder.set_width(width);
+        encoder.set_height(height);
+        encoder.set_format(format);
+        encoder.set_time_base((1, 25));
+        if *self == ScreenshotFormat::Jpeg {
+            // qscale 2, the best quality of mjpeg
+            encoder.set_flags(ffmpeg::codec::Flags::QSCALE);
+            unsafe { (*encoder.as_mut_ptr()).global_quality = 2 * ffmpeg::ffi::FF_QP2LAMBDA as i32 };
+        }
+        let mut encoder = encoder.open_as(codec)?;
+        encoder.send_frame(&picture)?;
+        encoder.send_eof()?;
+        let mut packet = ffmpeg::Packet::empty();
+        encoder.receive_packet(&mut packet)?;
+        Ok(packet.data().unwrap_or_default().to_vec())
+    }
+}
+
+pub struct Screenshot {}
+
+impl Screenshot {
+    pub const DIR_NAME: &'static str = "screenshots";
+
+    /// the file in the sub dir of the data dir, eg: "movie_01-02-03.456.png"
+    pub fn file_name(media_path: &str, position_ms: i64, format: ScreenshotFormat) -> PathBuf {
+        let stem = Path::new(media_path).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let name = format!("{stem}_{}.{}", ms_file_name(position_ms), format.extension());
+        data_dir_file(Self::DIR_NAME).join(name)
+    }
+
+    /// the image at the source resolution
+    pub fn image(frame: &ffmpeg::frame::Video) -> Result<egui::ColorImage, ffmpeg::Error> {
+        Player::frame_to_color_image(frame)
+    }
+
+    pub fn save(image: &egui::ColorImage, file: &Path, format: ScreenshotFormat) -> Result<(), anyhow::Error> {
+        let data = format.encode(image)?;
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(file, data)?;
+        Ok(())
+    }
+
+    /// decode the frame at the pts again through the filter spec, it has the subtitles if they are burned in, see [Player::screenshot]
+    pub fn decode_frame(file: &str, pts: i64, filter_spec: &str) -> Result<ffmpeg::frame::Video, anyhow::Error> {
+        let mut input = ffmpeg::format::input(&Path::new(file))?;
+        let (video_index, time_base, mut decoder) = {
+            let stream = FfmpegKit::video_stream(&input).ok_or(ffmpeg::Error::StreamNotFound)?;
+            let context = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?;
+            (stream.index(), stream.time_base(), FfmpegKit::video_decoder(context, &stream)?)
+        };
+        let ts = pts.rescale(time_base, AV_TIME_BASE_RATIONAL);
+        input.seek(ts, ..ts)?;
+        let mut graph = if filter_spec.is_empty() {
+            None
+        } else {
+            Some(Player::graph(&decoder, filter_spec, time_base)?)
+        };
+        let mut frame = ffmpeg::frame::Video::empty();
+        for (stream, packet) in input.packets() {
+            if stream.index() != video_index {
+                continue;
+            }
+            decoder.send_packet(&packet)?;
+            while decoder.receive_frame(&mut frame).is_ok() {
+                let Some(graph) = &mut graph else {
+                    if frame.pts().is_none_or(|t| t >= pts) {
+                        return Ok(frame);
+                    }
+                    continue;
+                };
+                graph.get("in").expect("").source().add(&frame)?;
+                let mut filter_frame = ffmpeg::frame::Video::empty();
+                while graph.get("out").expect("").sink().frame(&mut filter_frame).is_ok() {
+                    if filter_frame.pts().is_none_or(|t| t >= pts) {
+                        return Ok(filter_frame);
+                    }
+                }
+            }
+        }
+        Err(anyhow::anyhow!("no frame at {}ms", pts.rescale(time_base, MILLISECOND_TIME_BASE)))
+    }
+}
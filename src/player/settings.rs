@@ -0,0 +1,125 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::player::kits::data_dir_file;
+
+/// the settings of the app, one "key=value" per line in a text file.
+/// the line breaks and backslashes are escaped, and "=" is escaped in the keys, so the keys can be file paths
+#[derive(Default, Clone, Debug)]
+pub struct Settings {
+    file: PathBuf,
+    values: BTreeMap<String, String>,
+}
+
+impl Settings {
+    pub const FILE_NAME: &'static str = "settings.txt";
+
+    /// load the settings from the file in the data dir
+    pub fn load_default() -> Self {
+        Self::load(&data_dir_file(Self::FILE_NAME))
+    }
+
+    pub fn load(file: &Path) -> Self {
+        let mut values = BTreeMap::new();
+        match fs::read_to_string(file) {
+            Err(e) => log::debug!("{e}"),
+            Ok(text) => {
+                for line in text.lines().filter(|l| !l.trim().is_empty() && !l.starts_with('#')) {
+                    if let Some((key, value)) = line.split_once('=') {
+                        values.insert(unescape(key), unescape(value));
+                    }
+                }
+            }
+        }
+        Self {
+            file: file.to_path_buf(),
+            values,
+        }
+    }
+
+    pub fn save(&self) -> Result<(), anyhow::Error> {
+        let mut f = fs::File::create(&self.file)?;
+        for (key, value) in &self.values {
+            writeln!(f, "{}={}", escape(key, true), escape(value, false))?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// set the value and save the settings
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.insert(key, value);
+        if let Err(e) = self.save() {
+            log::error!("{e}");
+        }
+    }
+
+    /// set the value without saving, see [Settings::save]
+    pub fn insert(&mut self, key: &str, value: &str) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    /// remove the value without saving, see [Settings::save]
+    pub fn remove(&mut self, key: &str) {
+        self.values.remove(key);
+    }
+}
+
+fn escape(s: &str, is_key: bool) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '=' if is_key => escaped.push_str("\\e"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('e') => unescaped.push('='),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_save_load() {
+        let file = std::env::temp_dir().join("test_settings.txt");
+        let mut settings = Settings::load(&file);
+        settings.insert("display_mode", "fit");
+        settings.insert("video_filters_raw", "eq=contrast=1.2,drawtext=text='a\\:b'");
+        settings.insert("color_adjust:C:\\videos\\a=b\tc\nd.mkv", "0.1,1,1,1,0");
+        settings.insert("empty", "");
+        settings.save().unwrap();
+        let loaded = Settings::load(&file);
+        fs::remove_file(&file).unwrap();
+        assert_eq!(loaded.values, settings.values);
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    path,
+    sync::Arc,
+    time::Duration,
+};
+
+use ffmpeg::Rescale;
+
+use crate::{
+    kits::{Deque, Shared, new_deque},
+    player::{AV_TIME_BASE_RATIONAL, FrameConverter, MILLISECOND_TIME_BASE, PlayerState, VideoTransform, kits::FfmpegKit},
+};
+
+/// width of the thumbnail, the height is computed by the aspect of video
+pub const THUMBNAIL_WIDTH: u32 = 160;
+/// max count of the thumbnail textures in the cache
+pub const THUMBNAIL_CACHE_SIZE: usize = 64;
+/// max count of packets to read for one thumbnail
+const THUMBNAIL_MAX_PACKETS: usize = 512;
+/// the pause between two thumbnails while playing, the decoding of the player goes first
+const THUMBNAIL_PLAYING_INTERVAL: Duration = Duration::from_millis(100);
+
+/// thumbnails of the seekbar, they are decoded by a low priority thread which only decodes key frames.
+/// it pauses between the thumbnails while playing, and exits when the thumbnails are dropped
+pub struct Thumbnails {
+    /// the time span of one thumbnail
+    bucket_ms: i64,
+    requests: kanal::Sender<i64>,
+    /// the image is none if the decoding failed
+    results: Deque<(i64, Option<egui::ColorImage>)>,
+    /// lru cache, the last one is the most recently used
+    cache: VecDeque<(i64, egui::TextureHandle)>,
+    /// it is not requested again until it is evicted
+    last_request: Option<i64>,
+    /// the buckets which can not be decoded, they are not requested again
+    failed: HashSet<i64>,
+}
+
+impl Thumbnails {
+    pub fn new(ctx: &egui::Context, file: &str, duration_ms: i64, player_state: Shared<PlayerState>) -> Result<Self, anyhow::Error> {
+        let mut input = ffmpeg::format::input(&path::Path::new(file))?;
+        let (video_index, mut video_decoder, width, height, transform) = {
+            let video_stream = FfmpegKit::video_stream(&input).ok_or(ffmpeg::Error::StreamNotFound)?;
+            let transform = VideoTransform::from_stream(&video_stream);
+            let video_context = ffmpeg::codec::context::Context::from_parameters(video_stream.parameters())?;
+            let mut decoder = video_context.decoder();
+            decoder.skip_frame(ffmpeg::codec::discard::Discard::NonKey);
+            let video_decoder = decoder.video()?;
+            let width = THUMBNAIL_WIDTH.min(video_decoder.width());
+            // the anamorphic video is scaled to the square pixels
+            let display_width = video_decoder.width() as f32 * FfmpegKit::sample_aspect(&input, &video_stream);
+            let height = ((video_decoder.height() as f32 * width as f32 / display_width.max(1.)) as u32).max(1);
+            (video_stream.index(), video_decoder, width, height, transform)
+        };
+
+        let (request_sender, request_receiver) = kanal::unbounded();
+        let thumbnails = Self {
+            bucket_ms: (duration_ms / 200).max(1000),
+            requests: request_sender,
+            results: new_deque(),
+            cache: VecDeque::with_capacity(THUMBNAIL_CACHE_SIZE),
+            last_request: None,
+            failed: HashSet::new(),
+        };
+
+        let bucket_ms = thumbnails.bucket_ms;
+        let results = thumbnails.results.clone();
+        let ctx = ctx.clone();
+        let _ = std::thread::Builder::new().name("thumbnail".to_string()).spawn(move || {
+            let mut converter = FrameConverter::new(0);
+            // the receiving fails after the sender is dropped
+            while let Ok(mut bucket) = request_receiver.recv() {
+                // only the newest request is useful
+                while let Ok(Some(newer)) = request_receiver.try_recv() {
+                    bucket = newer;
+                }
+                let ms = bucket * bucket_ms + bucket_ms / 2;
+                let image = match Self::decode_key_frame(&mut input, &mut video_decoder, video_index, ms) {
+                    Err(e) => {
+                        log::debug!("thumbnail: {e}");
+                        None
+                    }
+                    Ok(frame) => match converter.convert(&frame, width, height) {
+                        Err(e) => {
+                            log::error!("{e}");
+                            None
+                        }
+                        Ok(image) => Some(transform.apply_image(Arc::unwrap_or_clone(image))),
+                    },
+                };
+                results.lock().push_back((bucket, image));
+                ctx.request_repaint();
+                if player_state.get() == PlayerState::Playing {
+                    std::thread::sleep(THUMBNAIL_PLAYING_INTERVAL);
+                }
+            }
+            log::info!("thumbnail exit");
+        });
+        Ok(thumbnails)
+    }
+
+    /// return the thumbnail texture of the ms, if it is not in the cache, request it and return None
+    pub fn texture(&mut self, ctx: &egui::Context, ms: i64) -> Option<egui::TextureHandle> {
+        let results: Vec<_> = self.results.lock().drain(..).collect();
+        for (bucket, image) in results {
+            let Some(image) = image else {
+                self.failed.insert(bucket);
+                continue;
+            };
+            let texture = ctx.load_texture(format!("thumbnail_{bucket}"), image, egui::TextureOptions::LINEAR);
+            self.cache.retain(|(b, _)| *b != bucket);
+            self.cache.push_back((bucket, texture));
+            while self.cache.len() > THUMBNAIL_CACHE_SIZE {
+                if let Some((evicted, _)) = self.cache.pop_front()
+                    && self.last_request == Some(evicted)
+                {
+                    self.last_request = None;
+                }
+            }
+        }
+
+        let bucket = ms / self.bucket_ms;
+        if let Some(i) = self.cache.iter().position(|(b, _)| *b == bucket) {
+            let item = self.cache.remove(i).expect("");
+            let texture = item.1.clone();
+            self.cache.push_back(item);
+            return Some(texture);
+        }
+        if self.last_request != Some(bucket) && !self.failed.contains(&bucket) {
+            self.last_request = Some(bucket);
+            if let Err(e) = self.requests.send(bucket) {
+                log::error!("{e}");
+            }
+        }
+        None
+    }
+
+    fn decode_key_frame(
+        input: &mut ffmpeg::format::context::Input,
+        video_decoder: &mut ffmpeg::decoder::Video,
+        video_index: usize,
+        ms: i64,
+    ) -> Result<ffmpeg::frame::Video, anyhow::Error> {
+        let ts = ms.rescale(MILLISECOND_TIME_BASE, AV_TIME_BASE_RATIONAL);
+        input.seek(ts, ..ts)?;
+        video_decoder.flush();
+        let mut frame = ffmpeg::frame::Video::empty();
+        for (stream, packet) in input.packets().take(THUMBNAIL_MAX_PACKETS) {
+            if stream.index() != video_index || !packet.is_key() {
+                continue;
+            }
+            video_decoder.send_packet(&packet)?;
+            if video_decoder.receive_frame(&mut frame).is_ok() {
+                return Ok(frame);
+            }
+        }
+        Err(ffmpeg::Error::Eof.into())
+    }
+}
@@ -0,0 +1,101 @@
+use ffmpeg::color::{Primaries, TransferCharacteristic};
+
+/// the hdr video, it is detected by the transfer characteristic of the stream
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hdr {
+    pub transfer: TransferCharacteristic,
+    pub primaries: Primaries,
+}
+
+impl Hdr {
+    /// some if the transfer is PQ(HDR10) or HLG
+    pub fn detect(transfer: TransferCharacteristic, primaries: Primaries) -> Option<Self> {
+        match transfer {
+            TransferCharacteristic::SMPTE2084 | TransferCharacteristic::ARIB_STD_B67 => Some(Self { transfer, primaries }),
+            _ => None,
+        }
+    }
+
+    pub fn from_decoder(decoder: &ffmpeg::decoder::Video) -> Option<Self> {
+        Self::detect(decoder.color_transfer_characteristic(), decoder.color_primaries())
+    }
+
+    pub fn name(&self) -> &'static str {
+        if self.transfer == TransferCharacteristic::SMPTE2084 {
+            "HDR10 (PQ)"
+        } else {
+            "HLG"
+        }
+    }
+
+    /// the input options of zscale, the untagged primaries are bt2020
+    fn zscale_input(&self) -> String {
+        let tin = if self.transfer == TransferCharacteristic::SMPTE2084 {
+            "smpte2084"
+        } else {
+            "arib-std-b67"
+        };
+        let pin = if self.primaries == Primaries::BT709 { "bt709" } else { "bt2020" };
+        format!("tin={tin}:pin={pin}")
+    }
+}
+
+/// the operators of the filter "tonemap", they map the hdr to sdr
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum ToneMap {
+    /// show the hdr picture as it is
+    Off,
+    Clip,
+    Linear,
+    Gamma,
+    Reinhard,
+    #[default]
+    Hable,
+    Mobius,
+}
+
+impl ToneMap {
+    pub const ALL: [ToneMap; 7] = [
+        ToneMap::Off,
+        ToneMap::Clip,
+        ToneMap::Linear,
+        ToneMap::Gamma,
+        ToneMap::Reinhard,
+        ToneMap::Hable,
+        ToneMap::Mobius,
+    ];
+
+    /// the name of the operator in the filter "tonemap"
+    pub fn name(&self) -> &'static str {
+        match self {
+            ToneMap::Off => "off",
+            ToneMap::Clip => "clip",
+            ToneMap::Linear => "linear",
+            ToneMap::Gamma => "gamma",
+            ToneMap::Reinhard => "reinhard",
+            ToneMap::Hable => "hable",
+            ToneMap::Mobius => "mobius",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.name() == name)
+    }
+
+    /// linearize by zscale, map to bt709 sdr by tonemap, then convert back to yuv.
+    /// empty if it is off, or zscale(libzimg) is not in the ffmpeg
+    pub fn spec(&self, hdr: &Hdr) -> String {
+        if *self == ToneMap::Off {
+            return String::new();
+        }
+        if ffmpeg::filter::find("zscale").is_none() || ffmpeg::filter::find("tonemap").is_none() {
+            log::warn!("no zscale or tonemap filter, the hdr video is not tone mapped");
+            return String::new();
+        }
+        format!(
+            "zscale={}:t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,tonemap=tonemap={}:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p",
+            hdr.zscale_input(),
+            self.name()
+        )
+    }
+}
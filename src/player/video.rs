@@ -1,4 +1,7 @@
-use std::fmt::{Debug, Formatter};
+use std::{
+    fmt::{Debug, Formatter},
+    sync::Arc,
+};
 
 #[derive(Default, Clone)]
 pub struct VideoPlayFrame {
@@ -7,7 +10,14 @@ pub struct VideoPlayFrame {
     pub pts: i64,
     pub duration: i64,
     pub timestamp: i64,
-    pub color_image: egui::ColorImage,
+    /// the seek serial when it is decoded
+    pub serial: u64,
+    /// the uv rect(min x, min y, max x, max y) without black bars, it is detected by the filter "cropdetect"
+    pub crop: Option<[f32; 4]>,
+    /// the picture has the alpha channel, it is shown over the background
+    pub alpha: bool,
+    /// the rgba image from [crate::player::ImagePool], it is reused after the texture uploaded it
+    pub color_image: Arc<egui::ColorImage>,
 }
 
 impl Debug for VideoPlayFrame {
@@ -0,0 +1,187 @@
+use crate::player::Settings;
+
+/// the named video filters of libavfilter, they can be enabled from the ui
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum VideoFilter {
+    Yadif,
+    Bwdif,
+    Hqdn3d,
+    Unsharp,
+}
+
+impl VideoFilter {
+    pub const ALL: [VideoFilter; 4] = [VideoFilter::Yadif, VideoFilter::Bwdif, VideoFilter::Hqdn3d, VideoFilter::Unsharp];
+
+    /// the name of the filter in libavfilter
+    pub fn name(&self) -> &'static str {
+        match self {
+            VideoFilter::Yadif => "yadif",
+            VideoFilter::Bwdif => "bwdif",
+            VideoFilter::Hqdn3d => "hqdn3d",
+            VideoFilter::Unsharp => "unsharp",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            VideoFilter::Yadif => "Deinterlace (yadif)",
+            VideoFilter::Bwdif => "Deinterlace (bwdif)",
+            VideoFilter::Hqdn3d => "Denoise (hqdn3d)",
+            VideoFilter::Unsharp => "Sharpen (unsharp)",
+        }
+    }
+
+    /// the filter with options, the deinterlace filters only process the interlaced frames
+    pub fn spec(&self) -> &'static str {
+        match self {
+            VideoFilter::Yadif => "yadif=mode=send_frame:deint=interlaced",
+            VideoFilter::Bwdif => "bwdif=mode=send_frame:deint=interlaced",
+            VideoFilter::Hqdn3d => "hqdn3d",
+            VideoFilter::Unsharp => "unsharp=5:5:1.0",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.name() == name)
+    }
+}
+
+/// the video filter chain set by the user, it is saved in the [Settings].
+/// the enabled filter names split by ",", and the raw libavfilter string
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct VideoFilters {
+    /// the enabled filters, in the order of [VideoFilter::ALL]
+    pub enabled: Vec<VideoFilter>,
+    /// raw libavfilter string, eg: "eq=contrast=1.2,hflip". it is after the enabled filters
+    pub raw: String,
+}
+
+impl VideoFilters {
+    const ENABLED_KEY: &'static str = "video_filters";
+    const RAW_KEY: &'static str = "video_filters_raw";
+
+    pub fn load(settings: &Settings) -> Self {
+        let mut filters = Self::default();
+        for name in settings.get(Self::ENABLED_KEY).unwrap_or_default().split(',').filter(|n| !n.is_empty()) {
+            match VideoFilter::from_name(name) {
+                Some(f) => filters.set_enabled(f, true),
+                None => log::error!("unknown video filter: {name}"),
+            }
+        }
+        filters.raw = settings.get(Self::RAW_KEY).unwrap_or_default().to_string();
+        filters
+    }
+
+    /// set the filters to the settings and save them
+    pub fn save(&self, settings: &mut Settings) -> Result<(), anyhow::Error> {
+        let names: Vec<&str> = self.enabled.iter().map(|f| f.name()).collect();
+        settings.insert(Self::ENABLED_KEY, &names.join(","));
+        settings.insert(Self::RAW_KEY, &self.raw);
+        settings.save()
+    }
+
+    pub fn is_enabled(&self, filter: VideoFilter) -> bool {
+        self.enabled.contains(&filter)
+    }
+
+    pub fn set_enabled(&mut self, filter: VideoFilter, enabled: bool) {
+        self.enabled.retain(|f| *f != filter);
+        if enabled {
+            self.enabled.push(filter);
+            self.enabled.sort_by_key(|f| VideoFilter::ALL.iter().position(|a| a == f));
+        }
+    }
+
+    /// the filter chain string of libavfilter, empty if there is no filter
+    pub fn spec(&self) -> String {
+        let mut specs: Vec<&str> = self.enabled.iter().map(|f| f.spec()).collect();
+        if !self.raw.trim().is_empty() {
+            specs.push(self.raw.trim());
+        }
+        specs.join(",")
+    }
+}
+
+/// the rotation and flip of the picture. the rotation is clockwise degrees: 0, 90, 180 or 270,
+/// the flips are applied after the rotation
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VideoTransform {
+    pub rotation: u32,
+    pub hflip: bool,
+    pub vflip: bool,
+}
+
+impl VideoTransform {
+    /// the rotation of the display matrix in the stream side data, eg: the videos of phones
+    pub fn from_stream(stream: &ffmpeg::format::stream::Stream) -> Self {
+        let mut transform = Self::default();
+        for side_data in stream.side_data() {
+            if side_data.kind() != ffmpeg::codec::packet::side_data::Type::DisplayMatrix || side_data.data().len() < 9 * 4 {
+                continue;
+            }
+            // the angle of the display matrix is counterclockwise
+            let theta = unsafe { ffmpeg::ffi::av_display_rotation_get(side_data.data().as_ptr() as *const i32) };
+            if theta.is_nan() {
+                continue;
+            }
+            let degrees = (-theta).round() as i64;
+            transform.rotation = ((degrees.rem_euclid(360) + 45) / 90 * 90 % 360) as u32;
+        }
+        transform
+    }
+
+    pub fn rotate(&mut self, clockwise: bool) {
+        self.rotation = (self.rotation + if clockwise { 90 } else { 270 }) % 360;
+    }
+
+    /// the width and height are swapped
+    pub fn is_transposed(&self) -> bool {
+        self.rotation % 180 == 90
+    }
+
+    /// the filter chain string of libavfilter, empty if there is no transform
+    pub fn spec(&self) -> String {
+        let (mut hflip, mut vflip) = (self.hflip, self.vflip);
+        let mut specs = Vec::new();
+        match self.rotation {
+            90 => specs.push("transpose=clock"),
+            180 => {
+                hflip = !hflip;
+                vflip = !vflip;
+            }
+            270 => specs.push("transpose=cclock"),
+            _ => {}
+        }
+        if hflip {
+            specs.push("hflip");
+        }
+        if vflip {
+            specs.push("vflip");
+        }
+        specs.join(",")
+    }
+
+    /// transform the image by cpu, it is used for the images which are not from the filter graph
+    pub fn apply_image(&self, image: egui::ColorImage) -> egui::ColorImage {
+        if *self == Self::default() {
+            return image;
+        }
+        let [w, h] = image.size;
+        let (out_w, out_h) = if self.is_transposed() { (h, w) } else { (w, h) };
+        let mut pixels = Vec::with_capacity(w * h);
+        for y in 0..out_h {
+            for x in 0..out_w {
+                let x = if self.hflip { out_w - 1 - x } else { x };
+                let y = if self.vflip { out_h - 1 - y } else { y };
+                let (src_x, src_y) = match self.rotation {
+                    90 => (y, h - 1 - x),
+                    180 => (w - 1 - x, h - 1 - y),
+                    270 => (w - 1 - y, x),
+                    _ => (x, y),
+                };
+                pixels.push(image.pixels[src_y * w + src_x]);
+            }
+        }
+        egui::ColorImage::new([out_w, out_h], pixels)
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use crate::player::kits::data_dir_file;
+
+/// if the position is bigger than duration * [WATCHED_RATIO], the file is watched
+pub const WATCHED_RATIO: f64 = 0.95;
+/// do not offer to resume, if the position is less than it
+pub const MIN_RESUME_MS: i64 = 5 * 1000;
+
+/// the play record of one file, it is identified by path, size and modified time
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct WatchRecord {
+    pub path: String,
+    pub size: u64,
+    /// modified time, seconds since unix epoch
+    pub modified: i64,
+    pub position_ms: i64,
+    pub duration_ms: i64,
+    pub volume: f64,
+    pub watched: bool,
+}
+
+impl WatchRecord {
+    pub fn new(path: &str, position_ms: i64, duration_ms: i64, volume: f64) -> Option<Self> {
+        let (size, modified) = Self::file_id(path)?;
+        let watched = duration_ms > 0 && position_ms as f64 >= duration_ms as f64 * WATCHED_RATIO;
+        Some(Self {
+            path: path.to_string(),
+            size,
+            modified,
+            position_ms,
+            duration_ms,
+            volume,
+            watched,
+        })
+    }
+
+    /// size and modified time of the file
+    fn file_id(path: &str) -> Option<(u64, i64)> {
+        let meta = fs::metadata(path).ok()?;
+        let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+        Some((meta.len(), modified))
+    }
+
+    /// the position to resume, none if the file is watched or the position is too small
+    pub fn resume_ms(&self) -> Option<i64> {
+        if self.watched || self.position_ms < MIN_RESUME_MS {
+            None
+        } else {
+            Some(self.position_ms)
+        }
+    }
+
+    /// one line: position_ms, duration_ms, volume, watched, size, modified, path. split by tab, the path is the last so it can contain tabs
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.position_ms, self.duration_ms, self.volume, self.watched as u8, self.size, self.modified, self.path
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut it = line.splitn(7, '\t');
+        Some(Self {
+            position_ms: it.next()?.parse().ok()?,
+            duration_ms: it.next()?.parse().ok()?,
+            volume: it.next()?.parse().ok()?,
+            watched: it.next()? == "1",
+            size: it.next()?.parse().ok()?,
+            modified: it.next()?.parse().ok()?,
+            path: it.next()?.to_string(),
+        })
+    }
+}
+
+/// watch history of all played files, it is saved to a text file
+pub struct WatchHistory {
+    file: PathBuf,
+    records: HashMap<String, WatchRecord>,
+}
+
+impl WatchHistory {
+    pub const FILE_NAME: &'static str = "watch_history.txt";
+
+    /// load the history from the file in the data dir
+    pub fn load_default() -> Self {
+        Self::load(&data_dir_file(Self::FILE_NAME))
+    }
+
+    pub fn load(file: &Path) -> Self {
+        let mut records = HashMap::new();
+        match fs::read_to_string(file) {
+            Err(e) => log::debug!("{e}"),
+            Ok(text) => {
+                for record in text.lines().filter_map(WatchRecord::from_line) {
+                    records.insert(record.path.clone(), record);
+                }
+            }
+        }
+        Self {
+            file: file.to_path_buf(),
+            records,
+        }
+    }
+
+    pub fn save(&self) -> Result<(), anyhow::Error> {
+        let mut records: Vec<_> = self.records.values().collect();
+        records.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut f = fs::File::create(&self.file)?;
+        for record in records {
+            writeln!(f, "{}", record.to_line())?;
+        }
+        Ok(())
+    }
+
+    /// the record of the file, none if the file is changed(size or modified time) after the record
+    pub fn get(&self, path: &str) -> Option<&WatchRecord> {
+        let record = self.records.get(path)?;
+        match WatchRecord::file_id(path) {
+            Some((size, modified)) if size == record.size && modified == record.modified => Some(record),
+            _ => None,
+        }
+    }
+
+    /// the path with line breaks can not be saved in one line, it is not recorded
+    pub fn update(&mut self, record: WatchRecord) {
+        if record.path.contains(['\n', '\r']) {
+            log::debug!("not recorded: {:?}", record.path);
+            return;
+        }
+        self.records.insert(record.path.clone(), record);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_save_load() {
+        let file = std::env::temp_dir().join("test_watch_history.txt");
+        let mut history = WatchHistory::load(&file);
+        let record = WatchRecord {
+            path: "/videos/a\tb.mkv".to_string(),
+            size: 1024,
+            modified: 1700000000,
+            position_ms: 60000,
+            duration_ms: 3600000,
+            volume: 0.5,
+            watched: false,
+        };
+        history.update(record.clone());
+        history.update(WatchRecord {
+            path: "/videos/a\nb.mkv".to_string(),
+            ..record.clone()
+        });
+        history.save().unwrap();
+        let loaded = WatchHistory::load(&file);
+        fs::remove_file(&file).unwrap();
+        assert_eq!(loaded.records.len(), 1);
+        assert_eq!(loaded.records.get(&record.path), Some(&record));
+    }
+}